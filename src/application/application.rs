@@ -1,9 +1,10 @@
-use crate::application::parser;
+use crate::application::{completion, parser};
+use crate::calculator;
 use crate::config::{Config, CustomCommands, SearchUrls};
 use crate::queryable::Queryable;
-use evalexpr;
 use log::info;
 use std::fs;
+use std::path::PathBuf;
 
 #[derive(Debug, Clone)]
 pub struct Application {
@@ -11,6 +12,15 @@ pub struct Application {
     pub exec: String,
     pub description: Option<String>,
     pub terminal: bool,
+    pub path: PathBuf,
+    pub categories: Vec<String>,
+}
+
+impl Application {
+    /// Returns the desktop file ID, the file name without its `.desktop` extension
+    pub fn desktop_id(&self) -> Option<&str> {
+        self.path.file_stem().and_then(|stem| stem.to_str())
+    }
 }
 
 pub fn scan_applications(config: &Config) -> Vec<Application> {
@@ -58,46 +68,6 @@ fn default_application_folders() -> Vec<String> {
     ]
 }
 
-/// Represents the type of item in the application list
-
-/// Attempts to evaluate an arithmetic expression
-///
-/// # Arguments
-/// * `expression` - The expression to evaluate
-///
-/// # Returns
-/// Result string if evaluation succeeds, None if invalid
-fn try_evaluate_expression(expression: &str) -> Option<String> {
-    let has_math_chars = expression.chars().any(|c| "+-*/()^%".contains(c));
-    if !has_math_chars {
-        return None;
-    }
-
-    match evalexpr::eval(expression) {
-        Ok(result) => format_expression_result(result),
-        Err(_) => None,
-    }
-}
-
-fn format_expression_result(result: evalexpr::Value) -> Option<String> {
-    match result {
-        evalexpr::Value::Float(f) => {
-            if f.fract() == 0.0 {
-                Some(format!("{}", f as i64))
-            } else {
-                Some(
-                    format!("{:.10}", f)
-                        .trim_end_matches('0')
-                        .trim_end_matches('.')
-                        .to_string(),
-                )
-            }
-        }
-        evalexpr::Value::Int(i) => Some(format!("{}", i)),
-        _ => Some(format!("{}", result)),
-    }
-}
-
 pub fn filter_applications(apps: &[Application], config: &Config, query: &str) -> Vec<Queryable> {
     if query.trim().is_empty() {
         return Vec::new();
@@ -105,17 +75,14 @@ pub fn filter_applications(apps: &[Application], config: &Config, query: &str) -
 
     let mut results: Vec<Queryable> = Vec::new();
 
-    if let Some(result) = try_evaluate_expression(query) {
-        results.push(Queryable::Calculator {
-            expression: query.to_string(),
-            result,
-        });
-        return results;
+    let calculator_results = calculator::evaluate(query, config);
+    if !calculator_results.is_empty() {
+        return calculator_results;
     }
 
     let query_lower = query.to_lowercase();
 
-    let custom_results = build_custom_commands(query_lower.clone(), &config.custom_commands);
+    let custom_results = build_custom_commands(query_lower.clone(), query, &config.custom_commands);
 
     results.extend(custom_results);
 
@@ -132,8 +99,11 @@ pub fn filter_applications(apps: &[Application], config: &Config, query: &str) -
         .collect();
 
     results.extend(app_results);
+    let found = !results.is_empty();
+
+    results.extend(calculator::constant(query, config));
 
-    if results.is_empty() {
+    if !found {
         let search_queryables = build_search_urls(query.to_string(), &config.search_urls);
         results.extend(search_queryables);
     }
@@ -154,37 +124,53 @@ fn build_search_urls(query: String, search_urls: &SearchUrls) -> Vec<Queryable>
     queryables
 }
 
-fn build_custom_commands(query: String, custom_commands: &CustomCommands) -> Vec<Queryable> {
+fn build_custom_commands(
+    query: String,
+    original_query: &str,
+    custom_commands: &CustomCommands,
+) -> Vec<Queryable> {
     let mut custom_results: Vec<Queryable> = Vec::new();
 
     for command in custom_commands.values() {
         let command_name_lower = command.name.to_lowercase();
 
         if command.accepts_arguments && query.starts_with(&command_name_lower) {
-            let arguments = extract_command_arguments(command.name.clone(), query.clone());
+            let arguments =
+                extract_command_arguments(command.name.clone(), original_query.to_string());
+            let suggestions = arguments
+                .as_deref()
+                .map(|arguments| completion::suggestions(command, arguments))
+                .unwrap_or_default();
+
             custom_results.push(Queryable::CustomCommand {
                 command: command.clone(),
                 arguments,
             });
-        } else if !command.accepts_arguments {
-            if command.name.to_lowercase().contains(&query)
-                || command
-                    .description
-                    .as_ref()
-                    .map_or(false, |desc| desc.to_lowercase().contains(&query))
-            {
-                custom_results.push(Queryable::CustomCommand {
+            custom_results.extend(suggestions.into_iter().map(|arguments| {
+                Queryable::CustomCommand {
                     command: command.clone(),
-                    arguments: None,
-                });
-            }
+                    arguments: Some(arguments),
+                }
+            }));
+        } else if command_name_lower.contains(&query)
+            || command
+                .description
+                .as_ref()
+                .map_or(false, |desc| desc.to_lowercase().contains(&query))
+        {
+            // Commands accepting arguments are listed too before their name is fully
+            // typed, so that Tab can complete it
+            custom_results.push(Queryable::CustomCommand {
+                command: command.clone(),
+                arguments: None,
+            });
         }
     }
 
     custom_results
 }
 
-fn extract_command_arguments(command_name: String, query: String) -> Option<String> {
+pub(super) fn extract_command_arguments(command_name: String, query: String) -> Option<String> {
     if query.len() > command_name.len() {
         if query.chars().nth(command_name.len()) == Some(' ') {
             let arguments = query[command_name.len() + 1..].to_string();
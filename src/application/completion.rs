@@ -0,0 +1,199 @@
+use log::error;
+use std::collections::HashMap;
+use std::fs;
+use std::sync::{Arc, LazyLock, Mutex, OnceLock};
+use std::time::Duration;
+
+use super::application::extract_command_arguments;
+use crate::config::{Completion, CustomCommand, CustomCommands};
+use crate::queryable::{self, Queryable};
+use crate::template;
+
+const MAX_SUGGESTIONS: usize = 20;
+
+/// How long a completion command may run before it is killed
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The output lines of a completion command, set once it has run
+type CommandOutput = Arc<OnceLock<Vec<String>>>;
+
+/// Output lines of completion commands, run once per launcher session
+static COMMAND_OUTPUTS: LazyLock<Mutex<HashMap<String, CommandOutput>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Lists the arguments a custom command could complete to from a fixed list or a glob
+///
+/// Completion commands may be slow, their suggestions come from `command_suggestions`
+/// off the main thread instead.
+///
+/// # Arguments
+/// * `command` - The custom command
+/// * `arguments` - The arguments typed so far, the last word being completed
+///
+/// # Returns
+/// The typed arguments with the last word replaced by each matching candidate,
+/// candidates starting with the word first
+pub fn suggestions(command: &CustomCommand, arguments: &str) -> Vec<String> {
+    let candidates = match &command.completion {
+        Some(Completion::Values(values)) => values.clone(),
+        Some(Completion::Glob(pattern)) => glob(pattern),
+        Some(Completion::Command(_)) | None => return Vec::new(),
+    };
+
+    complete(candidates, arguments)
+}
+
+/// Suggests arguments from the output of completion commands, running each command
+/// the first time it is needed
+///
+/// # Arguments
+/// * `commands` - The configured custom commands
+/// * `query` - The search query
+///
+/// # Returns
+/// A custom command result per suggestion, for the commands whose name starts the query
+pub fn command_suggestions(commands: &CustomCommands, query: &str) -> Vec<Queryable> {
+    let query_lower = query.to_lowercase();
+    let mut results = Vec::new();
+
+    for command in commands.values() {
+        let Some(Completion::Command(shell_command)) = &command.completion else {
+            continue;
+        };
+        if !command.accepts_arguments || !query_lower.starts_with(&command.name.to_lowercase()) {
+            continue;
+        }
+        let Some(arguments) = extract_command_arguments(command.name.clone(), query.to_string())
+        else {
+            continue;
+        };
+
+        let candidates = command_output(shell_command);
+        results.extend(
+            complete(candidates, &arguments)
+                .into_iter()
+                .map(|arguments| Queryable::CustomCommand {
+                    command: command.clone(),
+                    arguments: Some(arguments),
+                }),
+        );
+    }
+
+    results
+}
+
+fn complete(candidates: Vec<String>, arguments: &str) -> Vec<String> {
+    let (head, word) = match arguments.rfind(' ') {
+        Some(index) => arguments.split_at(index + 1),
+        None => ("", arguments),
+    };
+    let word_lower = word.to_lowercase();
+
+    let mut matches: Vec<String> = candidates
+        .into_iter()
+        .filter(|candidate| candidate != word && candidate.to_lowercase().contains(&word_lower))
+        .collect();
+    matches.sort_by_key(|candidate| !candidate.to_lowercase().starts_with(&word_lower));
+    matches.truncate(MAX_SUGGESTIONS);
+
+    matches
+        .into_iter()
+        .map(|candidate| format!("{head}{}", template::quote(&candidate)))
+        .collect()
+}
+
+/// Returns the output lines of a completion command, running it if no query did yet
+///
+/// Queries arriving while the command runs wait for its output instead of running it
+/// again.
+fn command_output(command: &str) -> Vec<String> {
+    let output = COMMAND_OUTPUTS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .entry(command.to_string())
+        .or_default()
+        .clone();
+
+    output.get_or_init(|| run_command(command)).clone()
+}
+
+fn run_command(command: &str) -> Vec<String> {
+    let argv = ["sh".to_string(), "-c".to_string(), command.to_string()];
+    match queryable::capture_output(&argv, COMMAND_TIMEOUT, None) {
+        Ok(mut lines) => {
+            lines
+                .iter_mut()
+                .for_each(|line| *line = line.trim().to_string());
+            lines.dedup();
+            lines
+        }
+        Err(message) => {
+            error!("Completion command {command} failed: {message}");
+            Vec::new()
+        }
+    }
+}
+
+/// Lists the paths matching a pattern whose last component may contain `*` and `?`
+///
+/// # Arguments
+/// * `pattern` - The pattern, like `~/notes/*.md`
+///
+/// # Returns
+/// The matching paths, written with the same directory as the pattern
+fn glob(pattern: &str) -> Vec<String> {
+    let (directory, name_pattern) = match pattern.rsplit_once('/') {
+        Some(("", name_pattern)) => ("/", name_pattern),
+        Some(split) => split,
+        None => (".", pattern),
+    };
+
+    let Ok(entries) = fs::read_dir(template::expand_home(directory)) else {
+        return Vec::new();
+    };
+
+    let mut paths: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| !name.starts_with('.') || name_pattern.starts_with('.'))
+        .filter(|name| matches_wildcard(name_pattern, name))
+        .map(|name| match directory {
+            "." => name,
+            "/" => format!("/{name}"),
+            _ => format!("{directory}/{name}"),
+        })
+        .collect();
+    paths.sort();
+    paths
+}
+
+fn matches_wildcard(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    n = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
@@ -1,6 +1,8 @@
 pub mod application;
+mod completion;
 mod parser;
 
 pub use application::Application;
 pub use application::filter_applications;
 pub use application::scan_applications;
+pub use completion::command_suggestions;
@@ -24,12 +24,15 @@ pub fn parse_desktop_file(path: &Path) -> Option<Application> {
     let exec = extract_exec(string_name, &section)?;
     let terminal = extract_terminal(&section);
     let description = section.attr("Comment").map(|value| value.to_string());
+    let categories = extract_categories(&section);
 
     let app = Application {
         name,
         exec,
         description,
         terminal,
+        path: path.to_path_buf(),
+        categories,
     };
 
     Some(app)
@@ -39,6 +42,16 @@ fn extract_terminal(section: &AttrSelector<&str>) -> bool {
     section.attr("Terminal").unwrap_or("false") == "true"
 }
 
+fn extract_categories(section: &AttrSelector<&str>) -> Vec<String> {
+    section
+        .attr("Categories")
+        .unwrap_or_default()
+        .split(';')
+        .filter(|category| !category.is_empty())
+        .map(|category| category.to_string())
+        .collect()
+}
+
 fn ensure_visible(string_name: &str, section: &AttrSelector<&str>) -> Option<()> {
     let hidden = section.attr("Hidden").unwrap_or("false") == "true";
     let no_display = section.attr("NoDisplay").unwrap_or("false") == "true";
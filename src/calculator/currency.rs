@@ -0,0 +1,174 @@
+use log::warn;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::calculator::Evaluator;
+use crate::calculator::eval::format_age;
+use crate::config::Currency;
+use crate::queryable::Queryable;
+
+/// Exchange rates as stored in the rates file, relative to `base`
+#[derive(Deserialize)]
+struct Rates {
+    base: String,
+    timestamp: Option<u64>,
+    rates: HashMap<String, f64>,
+}
+
+impl Rates {
+    fn rate(&self, code: &str) -> Option<f64> {
+        if code.eq_ignore_ascii_case(&self.base) {
+            return Some(1.0);
+        }
+
+        self.rates
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(code))
+            .map(|(_, rate)| *rate)
+    }
+}
+
+struct Conversion {
+    amount: f64,
+    from: String,
+    to: String,
+}
+
+/// Attempts to convert a query like `100 usd to eur` using the cached rates
+///
+/// # Arguments
+/// * `query` - The search query
+/// * `currency` - Currency configuration pointing at the rates file
+///
+/// # Returns
+/// A calculator result if the query is a conversion between known currencies
+pub fn try_convert(query: &str, currency: &Currency) -> Option<Queryable> {
+    let conversion = parse_conversion(query)?;
+    let path = rates_path(currency)?;
+    let (rates, updated_at) = load_rates(&path)?;
+
+    let rate = rates.rate(&conversion.to)? / rates.rate(&conversion.from)?;
+    let converted = conversion.amount * rate;
+
+    Some(Queryable::Calculator {
+        expression: format!(
+            "{} {} to {}",
+            format_number(conversion.amount, 2),
+            conversion.from,
+            conversion.to
+        ),
+        result: format!("{:.2}", converted),
+        note: Some(format!(
+            "1 {} = {} {} · rates updated {}",
+            conversion.from,
+            format_number(rate, 6),
+            conversion.to,
+            format_age(updated_at)
+        )),
+        evaluator: Evaluator::Currency,
+    })
+}
+
+/// Runs the configured update command and stores its output as the new rates file
+///
+/// # Arguments
+/// * `currency` - Currency configuration with the update command
+///
+/// # Returns
+/// The path of the written rates file, or a message describing the failure
+pub fn update_rates(currency: &Currency) -> Result<PathBuf, String> {
+    let command = currency
+        .update_command
+        .as_deref()
+        .ok_or("No currency.update_command configured")?;
+    let path = rates_path(currency).ok_or("Unable to resolve the rates file path")?;
+
+    let output = Command::new("sh")
+        .args(["-c", command])
+        .output()
+        .map_err(|error| format!("Unable to run `{command}`: {error}"))?;
+
+    if !output.status.success() {
+        return Err(format!("`{command}` exited with {}", output.status));
+    }
+
+    serde_json::from_slice::<Rates>(&output.stdout)
+        .map_err(|error| format!("`{command}` returned invalid rates: {error}"))?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|error| format!("Unable to create {}: {error}", parent.display()))?;
+    }
+    fs::write(&path, &output.stdout)
+        .map_err(|error| format!("Unable to write {}: {error}", path.display()))?;
+
+    Ok(path)
+}
+
+fn parse_conversion(query: &str) -> Option<Conversion> {
+    let query = query.trim().to_uppercase();
+    let (source, target) = query
+        .split_once(" TO ")
+        .or_else(|| query.split_once(" IN "))?;
+
+    let to = target.trim();
+    let source = source.trim();
+    let (amount, from) = source.split_at(
+        source
+            .trim_end_matches(|c: char| c.is_ascii_alphabetic())
+            .len(),
+    );
+
+    if !is_currency_code(from) || !is_currency_code(to) {
+        return None;
+    }
+
+    let amount = evalexpr::eval_number(amount.trim()).ok()?;
+
+    Some(Conversion {
+        amount,
+        from: from.to_string(),
+        to: to.to_string(),
+    })
+}
+
+fn is_currency_code(code: &str) -> bool {
+    code.len() == 3 && code.chars().all(|c| c.is_ascii_alphabetic())
+}
+
+fn rates_path(currency: &Currency) -> Option<PathBuf> {
+    match &currency.rates_file {
+        Some(path) => Some(PathBuf::from(path)),
+        None => dirs::data_dir().map(|dir| dir.join("palet").join("rates.json")),
+    }
+}
+
+fn load_rates(path: &Path) -> Option<(Rates, SystemTime)> {
+    let content = fs::read_to_string(path).ok()?;
+    let rates = serde_json::from_str::<Rates>(&content)
+        .map_err(|error| warn!("{} invalid rates file: {error}", path.display()))
+        .ok()?;
+
+    let updated_at = match rates.timestamp {
+        Some(timestamp) => UNIX_EPOCH + Duration::from_secs(timestamp),
+        None => fs::metadata(path).and_then(|meta| meta.modified()).ok()?,
+    };
+
+    Some((rates, updated_at))
+}
+
+fn format_number(value: f64, decimals: usize) -> String {
+    let formatted = format!("{:.*}", decimals, value);
+    if formatted.contains('.') {
+        formatted
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string()
+    } else {
+        formatted
+    }
+}
@@ -0,0 +1,488 @@
+use jiff::civil::{Date, DateTime, Time};
+use jiff::tz::{self, TimeZone};
+use jiff::{Span, Timestamp, Zoned};
+
+use crate::calculator::Evaluator;
+use crate::queryable::Queryable;
+
+/// Common abbreviations mapped to the zone they are usually meant for
+const ZONE_ABBREVIATIONS: [(&str, &str); 17] = [
+    ("pst", "America/Los_Angeles"),
+    ("pdt", "America/Los_Angeles"),
+    ("mst", "America/Denver"),
+    ("mdt", "America/Denver"),
+    ("cst", "America/Chicago"),
+    ("cdt", "America/Chicago"),
+    ("est", "America/New_York"),
+    ("edt", "America/New_York"),
+    ("gmt", "UTC"),
+    ("bst", "Europe/London"),
+    ("cet", "Europe/Berlin"),
+    ("cest", "Europe/Berlin"),
+    ("eet", "Europe/Athens"),
+    ("eest", "Europe/Athens"),
+    ("ist", "Asia/Kolkata"),
+    ("jst", "Asia/Tokyo"),
+    ("aest", "Australia/Sydney"),
+];
+
+const OPERATORS: [(&str, char); 2] = [(" + ", '+'), (" - ", '-')];
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+const DATETIME_FORMAT: &str = "%Y-%m-%d %H:%M %Z";
+
+enum Moment {
+    Date(Date),
+    Zoned(Zoned),
+}
+
+impl Moment {
+    /// Adds a span, turning a date into midnight of that day when the span has time units
+    /// that a date can't hold
+    fn checked_add(&self, span: Span) -> Option<Moment> {
+        match self {
+            Moment::Date(date) if has_time_units(span) => date
+                .to_zoned(TimeZone::system())
+                .ok()?
+                .checked_add(span)
+                .ok()
+                .map(Moment::Zoned),
+            Moment::Date(date) => date.checked_add(span).ok().map(Moment::Date),
+            Moment::Zoned(zoned) => zoned.checked_add(span).ok().map(Moment::Zoned),
+        }
+    }
+
+    fn to_zoned(&self) -> Option<Zoned> {
+        match self {
+            Moment::Date(date) => date.to_zoned(TimeZone::system()).ok(),
+            Moment::Zoned(zoned) => Some(zoned.clone()),
+        }
+    }
+
+    fn format(&self) -> String {
+        match self {
+            Moment::Date(date) => date.strftime(DATE_FORMAT).to_string(),
+            Moment::Zoned(zoned) => zoned.strftime(DATETIME_FORMAT).to_string(),
+        }
+    }
+
+    fn weekday(&self) -> String {
+        match self {
+            Moment::Date(date) => date.strftime("%A").to_string(),
+            Moment::Zoned(zoned) => zoned.strftime("%A").to_string(),
+        }
+    }
+}
+
+/// Attempts to evaluate a date or time query like `now + 3 days` or `3pm PST in Berlin`
+///
+/// # Arguments
+/// * `query` - The search query
+///
+/// # Returns
+/// Calculator results for the query, or None if it isn't a date query
+pub fn try_evaluate(query: &str) -> Option<Vec<Queryable>> {
+    let query = query.trim().to_lowercase();
+
+    week_number(&query)
+        .or_else(|| unix_time(&query))
+        .or_else(|| convert_time_zone(&query))
+        .or_else(|| date_arithmetic(&query))
+}
+
+fn week_number(query: &str) -> Option<Vec<Queryable>> {
+    let rest = query.strip_prefix("week number")?.trim();
+    let date = if rest.is_empty() {
+        Zoned::now().date()
+    } else {
+        parse_date(rest.strip_prefix("of ").unwrap_or(rest).trim())?
+    };
+
+    Some(vec![row(
+        query,
+        date.iso_week_date().week().to_string(),
+        format!("ISO week of {}", date.strftime(DATE_FORMAT)),
+    )])
+}
+
+fn unix_time(query: &str) -> Option<Vec<Queryable>> {
+    let rest = query.strip_prefix("unix ")?.trim();
+    if rest == "now" {
+        return Some(vec![row(
+            query,
+            Timestamp::now().as_second().to_string(),
+            "Current Unix timestamp".to_string(),
+        )]);
+    }
+
+    let timestamp = Timestamp::from_second(rest.parse().ok()?).ok()?;
+
+    Some(vec![
+        row(
+            query,
+            timestamp
+                .to_zoned(TimeZone::system())
+                .strftime(DATETIME_FORMAT)
+                .to_string(),
+            "Local time".to_string(),
+        ),
+        row(
+            query,
+            timestamp
+                .to_zoned(TimeZone::UTC)
+                .strftime(DATETIME_FORMAT)
+                .to_string(),
+            "UTC".to_string(),
+        ),
+    ])
+}
+
+fn convert_time_zone(query: &str) -> Option<Vec<Queryable>> {
+    let (source, target) = query.rsplit_once(" in ")?;
+    let target_zone = parse_time_zone(target.trim())?;
+    let converted = parse_zoned(source.trim())?.with_time_zone(target_zone);
+
+    Some(vec![row(
+        query,
+        converted.strftime(DATETIME_FORMAT).to_string(),
+        converted
+            .time_zone()
+            .iana_name()
+            .unwrap_or(target.trim())
+            .to_string(),
+    )])
+}
+
+fn date_arithmetic(query: &str) -> Option<Vec<Queryable>> {
+    let terms = split_terms(query);
+    if terms.len() < 2 {
+        return None;
+    }
+
+    let mut moment = parse_moment(terms[0].1)?;
+
+    if let [_, ('-', other)] = terms.as_slice()
+        && let Some(other) = parse_moment(other)
+    {
+        return difference(query, &moment, &other);
+    }
+
+    for (operator, term) in &terms[1..] {
+        let span = parse_span(term)?;
+        let span = if *operator == '-' {
+            span.negate()
+        } else {
+            span
+        };
+        moment = moment.checked_add(span)?;
+    }
+
+    Some(vec![row(query, moment.format(), moment.weekday())])
+}
+
+fn difference(query: &str, moment: &Moment, other: &Moment) -> Option<Vec<Queryable>> {
+    let days = match (moment, other) {
+        (Moment::Date(date), Moment::Date(other)) => date.since(*other).ok()?.get_days() as i64,
+        _ => {
+            let seconds = moment.to_zoned()?.timestamp().as_second()
+                - other.to_zoned()?.timestamp().as_second();
+            seconds / 86400
+        }
+    };
+
+    Some(vec![row(
+        query,
+        format!("{} days", days),
+        format!("{} weeks {} days", days / 7, days % 7),
+    )])
+}
+
+fn split_terms(query: &str) -> Vec<(char, &str)> {
+    let mut terms = Vec::new();
+    let mut operator = '+';
+    let mut rest = query;
+
+    loop {
+        let next = OPERATORS
+            .iter()
+            .filter_map(|(symbol, next_operator)| {
+                rest.find(symbol)
+                    .map(|index| (index, symbol.len(), *next_operator))
+            })
+            .min_by_key(|(index, _, _)| *index);
+
+        match next {
+            Some((index, length, next_operator)) => {
+                terms.push((operator, rest[..index].trim()));
+                operator = next_operator;
+                rest = &rest[index + length..];
+            }
+            None => {
+                terms.push((operator, rest.trim()));
+                return terms;
+            }
+        }
+    }
+}
+
+fn parse_moment(text: &str) -> Option<Moment> {
+    if text == "now" {
+        return Some(Moment::Zoned(Zoned::now()));
+    }
+    // A date parses from a date and time too, dropping the time
+    if !text.contains(':')
+        && let Some(date) = parse_date(text)
+    {
+        return Some(Moment::Date(date));
+    }
+
+    let datetime = text.parse::<DateTime>().ok()?;
+    datetime
+        .to_zoned(TimeZone::system())
+        .ok()
+        .map(Moment::Zoned)
+}
+
+fn parse_date(text: &str) -> Option<Date> {
+    let today = Zoned::now().date();
+    match text {
+        "today" => Some(today),
+        "tomorrow" => today.tomorrow().ok(),
+        "yesterday" => today.yesterday().ok(),
+        _ => text.parse::<Date>().ok(),
+    }
+}
+
+fn parse_zoned(text: &str) -> Option<Zoned> {
+    if let Some(zoned) = parse_zoned_in(text, TimeZone::system()) {
+        return Some(zoned);
+    }
+
+    text.match_indices(' ').find_map(|(index, _)| {
+        let time_zone = parse_time_zone(text[index + 1..].trim())?;
+        parse_zoned_in(text[..index].trim(), time_zone)
+    })
+}
+
+fn parse_zoned_in(text: &str, time_zone: TimeZone) -> Option<Zoned> {
+    if text == "now" {
+        return Some(Zoned::now().with_time_zone(time_zone));
+    }
+
+    let datetime = match parse_time(text) {
+        Some(time) => Zoned::now()
+            .with_time_zone(time_zone.clone())
+            .date()
+            .to_datetime(time),
+        None => text.parse::<DateTime>().ok()?,
+    };
+
+    datetime.to_zoned(time_zone).ok()
+}
+
+fn parse_time(text: &str) -> Option<Time> {
+    let (clock, meridiem_offset) = if let Some(clock) = text.strip_suffix("am") {
+        (clock.trim(), Some(0))
+    } else if let Some(clock) = text.strip_suffix("pm") {
+        (clock.trim(), Some(12))
+    } else {
+        (text, None)
+    };
+
+    let (hour, minute) = match clock.split_once(':') {
+        Some((hour, minute)) => (hour.parse::<i8>().ok()?, minute.parse::<i8>().ok()?),
+        None if meridiem_offset.is_some() => (clock.parse::<i8>().ok()?, 0),
+        None => return None,
+    };
+
+    let hour = match meridiem_offset {
+        Some(offset) if (1..=12).contains(&hour) => hour % 12 + offset,
+        Some(_) => return None,
+        None => hour,
+    };
+
+    Time::new(hour, minute, 0, 0).ok()
+}
+
+fn parse_time_zone(name: &str) -> Option<TimeZone> {
+    if name == "local" {
+        return Some(TimeZone::system());
+    }
+
+    let name = ZONE_ABBREVIATIONS
+        .iter()
+        .find(|(abbreviation, _)| *abbreviation == name)
+        .map_or(name, |(_, zone)| zone);
+
+    let database = tz::db();
+    if let Ok(time_zone) = database.get(name) {
+        return Some(time_zone);
+    }
+
+    let city = name.replace(' ', "_");
+    let zone_name = database.available().find(|zone_name| {
+        zone_name
+            .as_str()
+            .rsplit('/')
+            .next()
+            .is_some_and(|last| last.eq_ignore_ascii_case(&city))
+    })?;
+
+    database.get(zone_name.as_str()).ok()
+}
+
+fn parse_span(term: &str) -> Option<Span> {
+    let split_at = term.find(|c: char| !c.is_ascii_digit())?;
+    let (amount, unit) = term.split_at(split_at);
+    let amount = amount.parse::<i64>().ok()?;
+    let span = Span::new();
+
+    match unit.trim() {
+        "y" | "year" | "years" => span.try_years(amount).ok(),
+        "mo" | "month" | "months" => span.try_months(amount).ok(),
+        "w" | "week" | "weeks" => span.try_weeks(amount).ok(),
+        "d" | "day" | "days" => span.try_days(amount).ok(),
+        "h" | "hour" | "hours" => span.try_hours(amount).ok(),
+        "min" | "minute" | "minutes" => span.try_minutes(amount).ok(),
+        "s" | "second" | "seconds" => span.try_seconds(amount).ok(),
+        _ => None,
+    }
+}
+
+fn has_time_units(span: Span) -> bool {
+    span.get_hours() != 0 || span.get_minutes() != 0 || span.get_seconds() != 0
+}
+
+fn row(expression: &str, result: String, note: String) -> Queryable {
+    Queryable::Calculator {
+        expression: expression.to_string(),
+        result,
+        note: Some(note),
+        evaluator: Evaluator::DateTime,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Evaluates a query into its results and notes
+    fn evaluate(query: &str) -> Vec<(String, String)> {
+        try_evaluate(query)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|item| match item {
+                Queryable::Calculator { result, note, .. } => Some((result, note?)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn splits_terms_on_spaced_operators() {
+        assert_eq!(
+            split_terms("2024-01-31 + 1 month - 2 days"),
+            vec![('+', "2024-01-31"), ('+', "1 month"), ('-', "2 days")]
+        );
+        assert_eq!(split_terms("2024-01-31"), vec![('+', "2024-01-31")]);
+    }
+
+    #[test]
+    fn parses_dates() {
+        assert_eq!(
+            parse_date("2024-02-29"),
+            Some(jiff::civil::date(2024, 2, 29))
+        );
+        assert_eq!(parse_date("2023-02-29"), None);
+        assert_eq!(parse_date("tomorrow"), Zoned::now().date().tomorrow().ok());
+        assert!(matches!(parse_moment("2024-02-29"), Some(Moment::Date(_))));
+        assert!(matches!(
+            parse_moment("2024-02-29 10:30"),
+            Some(Moment::Zoned(_))
+        ));
+        assert!(parse_moment("someday").is_none());
+    }
+
+    #[test]
+    fn parses_times() {
+        assert_eq!(parse_time("3pm"), Some(jiff::civil::time(15, 0, 0, 0)));
+        assert_eq!(parse_time("9:30 am"), Some(jiff::civil::time(9, 30, 0, 0)));
+        assert_eq!(parse_time("12am"), Some(jiff::civil::time(0, 0, 0, 0)));
+        assert_eq!(parse_time("12pm"), Some(jiff::civil::time(12, 0, 0, 0)));
+        assert_eq!(parse_time("14:05"), Some(jiff::civil::time(14, 5, 0, 0)));
+        assert_eq!(parse_time("13pm"), None);
+        assert_eq!(parse_time("3"), None);
+    }
+
+    #[test]
+    fn parses_time_zones() {
+        let name = |text: &str| {
+            parse_time_zone(text).and_then(|zone| zone.iana_name().map(str::to_string))
+        };
+        assert_eq!(name("pst").as_deref(), Some("America/Los_Angeles"));
+        assert_eq!(name("europe/paris").as_deref(), Some("Europe/Paris"));
+        assert_eq!(name("berlin").as_deref(), Some("Europe/Berlin"));
+        assert_eq!(name("new york").as_deref(), Some("America/New_York"));
+        assert_eq!(name("atlantis"), None);
+    }
+
+    #[test]
+    fn parses_spans() {
+        assert_eq!(parse_span("3 days").map(|span| span.get_days()), Some(3));
+        assert_eq!(parse_span("90min").map(|span| span.get_minutes()), Some(90));
+        assert!(parse_span("2 fortnights").is_none());
+        assert!(parse_span("days").is_none());
+    }
+
+    #[test]
+    fn adds_spans_to_dates() {
+        assert_eq!(
+            evaluate("2024-01-31 + 1 month"),
+            vec![("2024-02-29".to_string(), "Thursday".to_string())]
+        );
+        assert_eq!(
+            evaluate("2024-03-01 - 1 week + 2d"),
+            vec![("2024-02-25".to_string(), "Sunday".to_string())]
+        );
+    }
+
+    #[test]
+    fn keeps_the_time_of_date_times() {
+        let result = evaluate("2024-02-29 10:30 + 2h");
+        assert!(result[0].0.starts_with("2024-02-29 12:30"));
+    }
+
+    #[test]
+    fn counts_days_between_dates() {
+        assert_eq!(
+            evaluate("2024-03-10 - 2024-03-01"),
+            vec![("9 days".to_string(), "1 weeks 2 days".to_string())]
+        );
+    }
+
+    #[test]
+    fn converts_between_time_zones() {
+        assert_eq!(
+            evaluate("2024-06-01 15:00 utc in berlin"),
+            vec![(
+                "2024-06-01 17:00 CEST".to_string(),
+                "Europe/Berlin".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn reads_unix_timestamps_and_week_numbers() {
+        assert_eq!(
+            evaluate("unix 0")[1],
+            ("1970-01-01 00:00 UTC".to_string(), "UTC".to_string())
+        );
+        assert_eq!(evaluate("week number of 2024-12-30")[0].0, "1");
+    }
+
+    #[test]
+    fn ignores_other_queries() {
+        assert!(try_evaluate("firefox").is_none());
+        assert!(try_evaluate("1 + 2").is_none());
+    }
+}
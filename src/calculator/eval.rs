@@ -0,0 +1,272 @@
+use crate::calculator::{Evaluator, currency, datetime, functions, history, precise, programmer};
+use crate::config::Config;
+use crate::queryable::Queryable;
+use evalexpr::{self, Context, HashMapContext};
+use std::time::SystemTime;
+
+const HISTORY_TRIGGER: &str = "=";
+const MATH_CHARS: &str = "+-*/()^%";
+const EXACT_MATH_CHARS: &str = "+-*/()^%!";
+
+/// Evaluates the query with every calculator source
+///
+/// # Arguments
+/// * `query` - The raw search query
+/// * `config` - Application configuration
+///
+/// # Returns
+/// Calculator results, empty if the query isn't something the calculator understands
+pub fn evaluate(query: &str, config: &Config) -> Vec<Queryable> {
+    if query.trim() == HISTORY_TRIGGER {
+        return history::recent();
+    }
+
+    if let Some(conversion) = currency::try_convert(query, &config.currency) {
+        return vec![conversion];
+    }
+
+    if let Some(results) = datetime::try_evaluate(query) {
+        return results;
+    }
+
+    if let Some(results) = programmer::try_evaluate(query) {
+        return results;
+    }
+
+    if config.calculator.exact
+        && has_math_chars(query, EXACT_MATH_CHARS)
+        && let Some(result) = precise::try_evaluate(query, &config.calculator)
+    {
+        return vec![Queryable::Calculator {
+            expression: query.to_string(),
+            result,
+            note: None,
+            evaluator: Evaluator::Exact,
+        }];
+    }
+
+    let assignment = history::parse_assignment(query);
+    if assignment.is_none() && !mentions_math(query) {
+        return Vec::new();
+    }
+
+    let degrees = config.calculator.degrees;
+    let mut context = history::context();
+    functions::register(&mut context, degrees);
+
+    if let Some((name, expression)) = assignment {
+        return evaluate_expression(expression, &context, degrees)
+            .map(|result| Queryable::Calculator {
+                expression: query.trim().to_string(),
+                result,
+                note: Some(format!("Assign to {}", name)),
+                evaluator: Evaluator::Arithmetic,
+            })
+            .into_iter()
+            .collect();
+    }
+
+    evaluate_expression(query, &context, degrees)
+        .map(|result| Queryable::Calculator {
+            expression: query.to_string(),
+            result,
+            note: None,
+            evaluator: Evaluator::Arithmetic,
+        })
+        .into_iter()
+        .collect()
+}
+
+/// Evaluates a query naming a constant alone, like `pi`
+///
+/// `evaluate` leaves these out so that the query still finds applications, this value
+/// being listed next to them instead.
+///
+/// # Arguments
+/// * `query` - The raw search query
+/// * `config` - Application configuration
+///
+/// # Returns
+/// The value of the constant, or of the variable shadowing it, none for other queries
+pub fn constant(query: &str, config: &Config) -> Option<Queryable> {
+    let name = query.trim();
+    if !functions::is_constant(name) {
+        return None;
+    }
+
+    let degrees = config.calculator.degrees;
+    let mut context = history::context();
+    functions::register(&mut context, degrees);
+
+    evaluate_expression(name, &context, degrees).map(|result| Queryable::Calculator {
+        expression: name.to_string(),
+        result,
+        note: None,
+        evaluator: Evaluator::Arithmetic,
+    })
+}
+
+/// Lists the steps evaluating an arithmetic expression goes through
+///
+/// # Arguments
+/// * `expression` - The expression, possibly an assignment like `x = 2 * 3`
+/// * `config` - Application configuration
+///
+/// # Returns
+/// The expression as evaluated, the values of the variables it uses and its result,
+/// empty if it isn't an arithmetic expression
+pub fn steps(expression: &str, config: &Config) -> Vec<String> {
+    let expression = history::parse_assignment(expression)
+        .map_or(expression, |(_, expression)| expression)
+        .trim();
+    let degrees = config.calculator.degrees;
+    let mut context = history::context();
+    functions::register(&mut context, degrees);
+
+    let converted =
+        functions::insert_multiplications(&functions::convert_angles(expression, degrees));
+    let Ok(tree) = evalexpr::build_operator_tree(&converted) else {
+        return Vec::new();
+    };
+
+    let mut steps = vec![expression.to_string()];
+    if converted != expression {
+        steps.push(format!("= {}", converted));
+    }
+
+    let mut variables: Vec<&str> = Vec::new();
+    for variable in tree.iter_variable_identifiers() {
+        if !variables.contains(&variable) {
+            variables.push(variable);
+        }
+    }
+    for variable in variables {
+        if let Some(value) = context
+            .get_value(variable)
+            .and_then(|value| format_expression_result(value.clone()))
+        {
+            steps.push(format!("{} = {}", variable, value));
+        }
+    }
+
+    if let Some(result) = evaluate_expression(expression, &context, degrees) {
+        steps.push(format!("= {}", result));
+    }
+    steps
+}
+
+/// Formats how long ago a moment was, like `5 min ago`
+///
+/// # Arguments
+/// * `moment` - The moment in the past
+///
+/// # Returns
+/// A short human readable age
+pub fn format_age(moment: SystemTime) -> String {
+    let seconds = SystemTime::now()
+        .duration_since(moment)
+        .unwrap_or_default()
+        .as_secs();
+
+    if seconds < 60 {
+        "just now".to_string()
+    } else if seconds < 3600 {
+        format!("{} min ago", seconds / 60)
+    } else if seconds < 86400 {
+        format!("{} h ago", seconds / 3600)
+    } else {
+        format!("{} days ago", seconds / 86400)
+    }
+}
+
+/// Checks whether a query looks like arithmetic worth evaluating
+///
+/// # Arguments
+/// * `expression` - The query to check
+///
+/// # Returns
+/// True if it contains an operator or mentions a math function or constant
+fn mentions_math(expression: &str) -> bool {
+    has_math_chars(expression, MATH_CHARS) || functions::mentions_math(expression)
+}
+
+fn has_math_chars(expression: &str, math_chars: &str) -> bool {
+    expression.chars().any(|c| math_chars.contains(c))
+}
+
+fn evaluate_expression(
+    expression: &str,
+    context: &HashMapContext,
+    degrees: bool,
+) -> Option<String> {
+    let expression =
+        functions::insert_multiplications(&functions::convert_angles(expression, degrees));
+    match evalexpr::eval_with_context(&expression, context) {
+        Ok(result) => format_expression_result(result),
+        Err(_) => None,
+    }
+}
+
+/// Formats an evalexpr result, whole floats beyond the i64 range in scientific notation
+fn format_expression_result(result: evalexpr::Value) -> Option<String> {
+    match result {
+        evalexpr::Value::Float(f) => {
+            if f.fract() == 0.0 && (i64::MIN as f64..i64::MAX as f64).contains(&f) {
+                Some(format!("{}", f as i64))
+            } else if f.fract() == 0.0 {
+                Some(format!("{:e}", f))
+            } else {
+                Some(
+                    format!("{:.10}", f)
+                        .trim_end_matches('0')
+                        .trim_end_matches('.')
+                        .to_string(),
+                )
+            }
+        }
+        evalexpr::Value::Int(i) => Some(format!("{}", i)),
+        _ => Some(format!("{}", result)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use evalexpr::Value;
+
+    #[test]
+    fn formats_whole_floats_as_integers() {
+        assert_eq!(
+            format_expression_result(Value::Float(1024.0)).as_deref(),
+            Some("1024")
+        );
+        assert_eq!(
+            format_expression_result(Value::Float(-3.0)).as_deref(),
+            Some("-3")
+        );
+    }
+
+    #[test]
+    fn formats_floats_beyond_i64_in_scientific_notation() {
+        assert_eq!(
+            format_expression_result(Value::Float(2f64.powi(100))).as_deref(),
+            Some("1.2676506002282294e30")
+        );
+        assert_eq!(
+            format_expression_result(Value::Float(-1e30)).as_deref(),
+            Some("-1e30")
+        );
+    }
+
+    #[test]
+    fn trims_fractions() {
+        assert_eq!(
+            format_expression_result(Value::Float(0.1 + 0.2)).as_deref(),
+            Some("0.3")
+        );
+        assert_eq!(
+            format_expression_result(Value::Float(2.5)).as_deref(),
+            Some("2.5")
+        );
+    }
+}
@@ -0,0 +1,325 @@
+use evalexpr::{
+    Context, ContextWithMutableFunctions, ContextWithMutableVariables, EvalexprResult, Function,
+    HashMapContext, Value,
+};
+use std::f64::consts::{E, PI, TAU};
+
+/// Golden ratio
+const PHI: f64 = 1.618_033_988_749_895;
+
+const CONSTANTS: [(&str, f64); 4] = [("pi", PI), ("e", E), ("tau", TAU), ("phi", PHI)];
+
+type MathFunction = (&'static str, fn(f64) -> f64);
+
+const FUNCTIONS: [MathFunction; 15] = [
+    ("sqrt", f64::sqrt),
+    ("cbrt", f64::cbrt),
+    ("ln", f64::ln),
+    ("log", f64::log10),
+    ("log2", f64::log2),
+    ("exp", f64::exp),
+    ("abs", f64::abs),
+    ("sinh", f64::sinh),
+    ("cosh", f64::cosh),
+    ("tanh", f64::tanh),
+    ("asinh", f64::asinh),
+    ("acosh", f64::acosh),
+    ("atanh", f64::atanh),
+    ("trunc", f64::trunc),
+    ("fract", f64::fract),
+];
+
+/// Trigonometric functions, taking an angle
+const TRIGONOMETRIC_FUNCTIONS: [MathFunction; 3] =
+    [("sin", f64::sin), ("cos", f64::cos), ("tan", f64::tan)];
+
+/// Inverse trigonometric functions, returning an angle
+const INVERSE_TRIGONOMETRIC_FUNCTIONS: [MathFunction; 3] = [
+    ("asin", f64::asin),
+    ("acos", f64::acos),
+    ("atan", f64::atan),
+];
+
+/// Other functions evalexpr already provides
+const BUILTIN_FUNCTIONS: [&str; 5] = ["floor", "ceil", "round", "min", "max"];
+
+const DEGREE_SUFFIXES: [&str; 2] = ["deg", "°"];
+const RADIAN_SUFFIXES: [&str; 1] = ["rad"];
+
+/// Adds the math functions and the constants not shadowed by a variable to the context
+///
+/// # Arguments
+/// * `context` - The evaluation context
+/// * `degrees` - Whether trigonometric functions work in degrees instead of radians
+pub fn register(context: &mut HashMapContext, degrees: bool) {
+    for (name, value) in CONSTANTS {
+        if context.get_value(name).is_none() {
+            let _ = context.set_value(name.to_string(), Value::Float(value));
+        }
+    }
+
+    for (name, function) in FUNCTIONS {
+        set_function(context, name, function);
+    }
+
+    for (name, function) in TRIGONOMETRIC_FUNCTIONS {
+        set_function(context, name, move |x| {
+            function(if degrees { x.to_radians() } else { x })
+        });
+    }
+
+    for (name, function) in INVERSE_TRIGONOMETRIC_FUNCTIONS {
+        set_function(context, name, move |x| {
+            let angle = function(x);
+            if degrees { angle.to_degrees() } else { angle }
+        });
+    }
+}
+
+/// Checks whether the expression calls a known function or uses a known constant
+///
+/// A constant alone, like `pi`, doesn't count so that it still finds applications, its
+/// value being listed next to them instead.
+///
+/// # Arguments
+/// * `expression` - The expression to check
+///
+/// # Returns
+/// True if the expression should be handed to the calculator
+pub fn mentions_math(expression: &str) -> bool {
+    let words: Vec<&str> = expression
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect();
+
+    words.iter().any(|word| {
+        is_function(word) || (words.len() > 1 && CONSTANTS.iter().any(|(name, _)| name == word))
+    })
+}
+
+/// Checks whether a name is one of the known constants
+pub fn is_constant(name: &str) -> bool {
+    CONSTANTS.iter().any(|(constant, _)| *constant == name)
+}
+
+/// Writes out the multiplications left implicit, like in `2 pi` or `3(1 + 2)`
+///
+/// A number or closing parenthesis followed by a name or opening parenthesis is
+/// multiplied by it, as is a closing parenthesis followed by a number. Function calls
+/// like `ln 10` or `sqrt(2)` are left alone.
+///
+/// # Arguments
+/// * `expression` - The expression to rewrite
+///
+/// # Returns
+/// The expression with an explicit `*` for every implicit multiplication
+pub fn insert_multiplications(expression: &str) -> String {
+    let mut rewritten = String::with_capacity(expression.len());
+    let mut previous = Token::Other;
+    let mut rest = expression;
+
+    while let Some(c) = rest.chars().next() {
+        if c.is_whitespace() {
+            rewritten.push(c);
+            rest = &rest[c.len_utf8()..];
+            continue;
+        }
+
+        let (token, length) = if c.is_ascii_digit() || c == '.' {
+            (Token::Number, number_length(rest))
+        } else if c.is_alphabetic() || c == '_' {
+            let length = rest
+                .find(|c: char| !c.is_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+            (Token::Name, length)
+        } else {
+            let token = match c {
+                '(' => Token::Open,
+                ')' => Token::Close,
+                _ => Token::Other,
+            };
+            (token, c.len_utf8())
+        };
+
+        if matches!(
+            (previous, token),
+            (Token::Number | Token::Close, Token::Name | Token::Open)
+                | (Token::Close, Token::Number)
+        ) {
+            rewritten.push_str(if rewritten.ends_with(char::is_whitespace) {
+                "* "
+            } else {
+                "*"
+            });
+        }
+
+        rewritten.push_str(&rest[..length]);
+        rest = &rest[length..];
+        previous = token;
+    }
+
+    rewritten
+}
+
+/// The kinds of tokens told apart to find implicit multiplications
+#[derive(Clone, Copy)]
+enum Token {
+    Number,
+    Name,
+    Open,
+    Close,
+    Other,
+}
+
+/// Returns the length of the number starting the text, with its exponent or radix
+/// prefix, like in `1.5e3` or `0x1f`
+fn number_length(text: &str) -> usize {
+    let end_of = |text: &str, start: usize, part: fn(char) -> bool| {
+        text[start..]
+            .find(|c: char| !part(c))
+            .map_or(text.len(), |end| start + end)
+    };
+
+    if ["0x", "0b", "0o"]
+        .iter()
+        .any(|prefix| text.starts_with(prefix))
+    {
+        return end_of(text, 2, |c| c.is_ascii_alphanumeric());
+    }
+
+    let end = end_of(text, 0, |c| c.is_ascii_digit() || c == '.');
+    match text[end..].strip_prefix(['e', 'E']) {
+        Some(exponent) if exponent.starts_with(|c: char| c.is_ascii_digit()) => {
+            end_of(text, end + 1, |c| c.is_ascii_digit())
+        }
+        _ => end,
+    }
+}
+
+/// Replaces angles written as `30deg`, `30°` or `1.2rad` by their value in the working unit
+///
+/// # Arguments
+/// * `expression` - The expression to rewrite
+/// * `degrees` - Whether trigonometric functions work in degrees instead of radians
+///
+/// # Returns
+/// The expression with plain numbers instead of angle literals
+pub fn convert_angles(expression: &str, degrees: bool) -> String {
+    let mut converted = String::with_capacity(expression.len());
+    let mut rest = expression;
+
+    while let Some(start) = rest.find(|c: char| c.is_ascii_digit()) {
+        converted.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let end = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rest.len());
+        let (number, after) = rest.split_at(end);
+
+        match (number.parse::<f64>(), angle_suffix(after)) {
+            (Ok(value), Some((suffix, is_degrees))) => {
+                let value = match (is_degrees, degrees) {
+                    (true, false) => value.to_radians(),
+                    (false, true) => value.to_degrees(),
+                    _ => value,
+                };
+                converted.push_str(&format!("({})", value));
+                rest = &after[suffix.len()..];
+            }
+            _ => {
+                converted.push_str(number);
+                rest = after;
+            }
+        }
+    }
+
+    converted.push_str(rest);
+    converted
+}
+
+fn angle_suffix(text: &str) -> Option<(&'static str, bool)> {
+    let suffixes = DEGREE_SUFFIXES
+        .iter()
+        .map(|suffix| (*suffix, true))
+        .chain(RADIAN_SUFFIXES.iter().map(|suffix| (*suffix, false)));
+
+    for (suffix, is_degrees) in suffixes {
+        if let Some(after) = text.strip_prefix(suffix)
+            && !after.starts_with(|c: char| c.is_ascii_alphanumeric())
+        {
+            return Some((suffix, is_degrees));
+        }
+    }
+    None
+}
+
+fn is_function(name: &str) -> bool {
+    FUNCTIONS
+        .iter()
+        .chain(TRIGONOMETRIC_FUNCTIONS.iter())
+        .chain(INVERSE_TRIGONOMETRIC_FUNCTIONS.iter())
+        .any(|(function, _)| *function == name)
+        || BUILTIN_FUNCTIONS.contains(&name)
+}
+
+fn set_function(
+    context: &mut HashMapContext,
+    name: &str,
+    function: impl Fn(f64) -> f64 + Clone + Send + Sync + 'static,
+) {
+    let _ = context.set_function(
+        name.to_string(),
+        Function::new(move |argument| -> EvalexprResult<Value> {
+            Ok(Value::Float(function(argument.as_number()?)))
+        }),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn multiplies_numbers_by_names_and_groups() {
+        assert_eq!(insert_multiplications("2 pi"), "2 * pi");
+        assert_eq!(insert_multiplications("2pi"), "2*pi");
+        assert_eq!(insert_multiplications("3(1 + 2)"), "3*(1 + 2)");
+        assert_eq!(insert_multiplications("(1 + 2)(3)"), "(1 + 2)*(3)");
+        assert_eq!(insert_multiplications("(2)3"), "(2)*3");
+        assert_eq!(insert_multiplications("2 ln 10"), "2 * ln 10");
+    }
+
+    #[test]
+    fn leaves_calls_and_literals_alone() {
+        for expression in [
+            "ln 10",
+            "sqrt(2)",
+            "log2(8)",
+            "1.5e3 + 2",
+            "0x1f",
+            "x = 2 * 3",
+        ] {
+            assert_eq!(insert_multiplications(expression), expression);
+        }
+    }
+
+    #[test]
+    fn lone_constants_are_left_to_the_applications() {
+        assert!(!mentions_math("pi"));
+        assert!(mentions_math("2 pi"));
+        assert!(mentions_math("sqrt 2"));
+        assert!(is_constant("tau"));
+        assert!(!is_constant("sqrt"));
+    }
+
+    #[test]
+    fn converts_angles_to_the_working_unit() {
+        assert_eq!(
+            convert_angles("sin 90deg", false),
+            format!("sin ({})", PI / 2.0)
+        );
+        assert_eq!(convert_angles("sin 90°", true), "sin (90)");
+        assert_eq!(convert_angles("90degrees", true), "90degrees");
+    }
+}
@@ -0,0 +1,185 @@
+use evalexpr::{ContextWithMutableVariables, HashMapContext, Value};
+use log::error;
+use rusqlite::{Connection, params};
+use std::cell::RefCell;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::calculator::Evaluator;
+use crate::calculator::eval::format_age;
+use crate::database;
+use crate::queryable::Queryable;
+
+const HISTORY_LIMIT: i64 = 20;
+const ANSWER_VARIABLE: &str = "ans";
+
+thread_local! {
+    /// The stored variables and `ans`, loaded from the database on first use
+    static CONTEXT: RefCell<Option<HashMapContext>> = const { RefCell::new(None) };
+}
+
+/// Returns an evaluation context holding the stored variables and `ans`
+///
+/// # Returns
+/// The context, empty if the database is unavailable
+pub fn context() -> HashMapContext {
+    CONTEXT.with(|context| {
+        context
+            .borrow_mut()
+            .get_or_insert_with(load_context)
+            .clone()
+    })
+}
+
+fn load_context() -> HashMapContext {
+    let mut context = HashMapContext::new();
+    let Some(connection) = database::open() else {
+        return context;
+    };
+
+    let mut variables = query_variables(&connection).unwrap_or_else(|error| {
+        error!("Unable to load calculator variables: {error}");
+        Vec::new()
+    });
+    if let Some(answer) = query_last_result(&connection) {
+        variables.push((ANSWER_VARIABLE.to_string(), answer));
+    }
+
+    for (name, value) in variables {
+        if let Some(value) = parse_value(&value) {
+            let _ = context.set_value(name, value);
+        }
+    }
+
+    context
+}
+
+/// Lists the most recent calculations, newest first
+///
+/// # Returns
+/// Calculator results for the stored history
+pub fn recent() -> Vec<Queryable> {
+    let Some(connection) = database::open() else {
+        return Vec::new();
+    };
+
+    query_history(&connection).unwrap_or_else(|error| {
+        error!("Unable to load calculator history: {error}");
+        Vec::new()
+    })
+}
+
+/// Stores a calculation in the history and, for assignments, the assigned variable
+///
+/// # Arguments
+/// * `expression` - The evaluated expression
+/// * `result` - The formatted result
+pub fn remember(expression: &str, result: &str) {
+    let assignment = parse_assignment(expression);
+    CONTEXT.with(|context| {
+        if let Some(context) = context.borrow_mut().as_mut()
+            && let Some(value) = parse_value(result)
+        {
+            if let Some((name, _)) = assignment {
+                let _ = context.set_value(name.to_string(), value.clone());
+            }
+            let _ = context.set_value(ANSWER_VARIABLE.to_string(), value);
+        }
+    });
+
+    let Some(connection) = database::open() else {
+        return;
+    };
+
+    let created_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64;
+
+    if let Err(error) = connection.execute(
+        "INSERT INTO calculator_history (expression, result, created_at) VALUES (?1, ?2, ?3)",
+        params![expression, result, created_at],
+    ) {
+        error!("Unable to store calculation: {error}");
+    }
+
+    if let Some((name, _)) = assignment
+        && let Err(error) = connection.execute(
+            "INSERT OR REPLACE INTO calculator_variables (name, value) VALUES (?1, ?2)",
+            params![name, result],
+        )
+    {
+        error!("Unable to store variable {name}: {error}");
+    }
+}
+
+/// Splits an assignment like `x = 42` into its variable name and expression
+///
+/// # Arguments
+/// * `query` - The search query
+///
+/// # Returns
+/// The variable name and the assigned expression, None if it isn't an assignment
+pub fn parse_assignment(query: &str) -> Option<(&str, &str)> {
+    let (name, expression) = query.split_once('=')?;
+    let name = name.trim();
+    let expression = expression.trim();
+
+    if expression.is_empty()
+        || expression.starts_with('=')
+        || name == ANSWER_VARIABLE
+        || !is_identifier(name)
+    {
+        return None;
+    }
+
+    Some((name, expression))
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn parse_value(value: &str) -> Option<Value> {
+    match evalexpr::eval(value) {
+        Ok(value @ (Value::Int(_) | Value::Float(_))) => Some(value),
+        _ => None,
+    }
+}
+
+fn query_variables(connection: &Connection) -> rusqlite::Result<Vec<(String, String)>> {
+    let mut statement = connection.prepare("SELECT name, value FROM calculator_variables")?;
+    let rows = statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    rows.collect()
+}
+
+fn query_last_result(connection: &Connection) -> Option<String> {
+    connection
+        .query_row(
+            "SELECT result FROM calculator_history ORDER BY id DESC LIMIT 1",
+            [],
+            |row| row.get(0),
+        )
+        .ok()
+}
+
+fn query_history(connection: &Connection) -> rusqlite::Result<Vec<Queryable>> {
+    let mut statement = connection.prepare(
+        "SELECT expression, result, created_at FROM calculator_history ORDER BY id DESC LIMIT ?1",
+    )?;
+    let rows = statement.query_map([HISTORY_LIMIT], |row| {
+        let created_at: i64 = row.get(2)?;
+        Ok(Queryable::Calculator {
+            expression: row.get(0)?,
+            result: row.get(1)?,
+            note: Some(format_age(
+                UNIX_EPOCH + Duration::from_secs(created_at.max(0) as u64),
+            )),
+            evaluator: Evaluator::History,
+        })
+    })?;
+    rows.collect()
+}
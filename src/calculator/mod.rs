@@ -0,0 +1,32 @@
+mod currency;
+mod datetime;
+mod eval;
+mod functions;
+mod history;
+mod precise;
+mod programmer;
+
+pub use currency::update_rates;
+pub use eval::{constant, evaluate, steps};
+pub use history::remember;
+
+/// The calculator source a result comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Evaluator {
+    /// Floating point arithmetic, with functions, constants and variables
+    Arithmetic,
+    /// Arithmetic on exact decimals
+    Exact,
+    Programmer,
+    DateTime,
+    Currency,
+    /// A calculation listed from the history
+    History,
+}
+
+impl Evaluator {
+    /// Whether activating a result records the calculation in the history
+    pub fn is_remembered(self) -> bool {
+        matches!(self, Evaluator::Arithmetic | Evaluator::Exact)
+    }
+}
@@ -0,0 +1,379 @@
+use bigdecimal::num_bigint::BigInt;
+use bigdecimal::{BigDecimal, ToPrimitive, Zero};
+use std::str::FromStr;
+
+use crate::config::Calculator;
+
+/// Largest exponent or factorial operand accepted, to keep evaluation instant
+const MAX_OPERAND: i64 = 10_000;
+
+/// Largest number of digits a power or factorial may produce
+const MAX_DIGITS: u64 = 10_000;
+
+/// Operators from the loosest to the tightest binding
+const PRECEDENCE: [&[char]; 2] = [&['+', '-'], &['*', '/', '%']];
+
+#[derive(Debug, Clone)]
+enum Token {
+    Number(BigDecimal),
+    Symbol(char),
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    position: usize,
+    options: &'a Calculator,
+}
+
+/// Attempts to evaluate an arithmetic expression without losing precision
+///
+/// # Arguments
+/// * `expression` - The expression, made of decimal numbers, `+ - * / % ^ !` and parentheses
+/// * `options` - Calculator configuration for precision and notation
+///
+/// # Returns
+/// The formatted result, or None if the expression uses anything else
+pub fn try_evaluate(expression: &str, options: &Calculator) -> Option<String> {
+    let mut parser = Parser {
+        tokens: tokenize(expression)?,
+        position: 0,
+        options,
+    };
+
+    let value = parser.binary(0)?;
+    if parser.position != parser.tokens.len() {
+        return None;
+    }
+
+    let value = if value.is_integer() {
+        value
+    } else {
+        value.with_prec(options.precision)
+    };
+
+    Some(format_decimal(&value, options.scientific))
+}
+
+fn tokenize(expression: &str) -> Option<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut rest = expression.trim_start();
+
+    while let Some(c) = rest.chars().next() {
+        if c.is_ascii_digit() || c == '.' {
+            let end = rest
+                .find(|c: char| !c.is_ascii_digit() && c != '.' && c != '_')
+                .unwrap_or(rest.len());
+            let literal = rest[..end].replace('_', "");
+            tokens.push(Token::Number(BigDecimal::from_str(&literal).ok()?));
+            rest = &rest[end..];
+        } else if "+-*/%^!()".contains(c) {
+            tokens.push(Token::Symbol(c));
+            rest = &rest[c.len_utf8()..];
+        } else {
+            return None;
+        }
+        rest = rest.trim_start();
+    }
+
+    Some(tokens)
+}
+
+impl Parser<'_> {
+    fn peek_symbol(&self) -> Option<char> {
+        match self.tokens.get(self.position) {
+            Some(Token::Symbol(symbol)) => Some(*symbol),
+            _ => None,
+        }
+    }
+
+    fn binary(&mut self, level: usize) -> Option<BigDecimal> {
+        if level == PRECEDENCE.len() {
+            return self.unary();
+        }
+
+        let mut left = self.binary(level + 1)?;
+        while let Some(symbol) = self.peek_symbol() {
+            if !PRECEDENCE[level].contains(&symbol) {
+                break;
+            }
+            self.position += 1;
+            let right = self.binary(level + 1)?;
+            left = self.apply(symbol, left, right)?;
+        }
+        Some(left)
+    }
+
+    fn unary(&mut self) -> Option<BigDecimal> {
+        match self.peek_symbol() {
+            Some('-') => {
+                self.position += 1;
+                Some(-self.unary()?)
+            }
+            Some('+') => {
+                self.position += 1;
+                self.unary()
+            }
+            _ => self.power(),
+        }
+    }
+
+    fn power(&mut self) -> Option<BigDecimal> {
+        let base = self.postfix()?;
+        if self.peek_symbol() != Some('^') {
+            return Some(base);
+        }
+
+        self.position += 1;
+        let exponent = self.unary()?;
+        self.pow(base, exponent)
+    }
+
+    fn postfix(&mut self) -> Option<BigDecimal> {
+        let mut value = self.primary()?;
+        while self.peek_symbol() == Some('!') {
+            self.position += 1;
+            value = factorial(&value)?;
+        }
+        Some(value)
+    }
+
+    fn primary(&mut self) -> Option<BigDecimal> {
+        let token = self.tokens.get(self.position)?.clone();
+        self.position += 1;
+
+        match token {
+            Token::Number(value) => Some(value),
+            Token::Symbol('(') => {
+                let value = self.binary(0)?;
+                if self.peek_symbol() != Some(')') {
+                    return None;
+                }
+                self.position += 1;
+                Some(value)
+            }
+            Token::Symbol(_) => None,
+        }
+    }
+
+    fn apply(&self, symbol: char, left: BigDecimal, right: BigDecimal) -> Option<BigDecimal> {
+        match symbol {
+            '+' => Some(left + right),
+            '-' => Some(left - right),
+            '*' => Some(left * right),
+            '/' => self.divide(left, right),
+            '%' if right.is_zero() => None,
+            '%' => Some(left % right),
+            _ => None,
+        }
+    }
+
+    /// Divides exactly when the quotient terminates, and otherwise rounds it a little
+    /// beyond the configured precision
+    fn divide(&self, left: BigDecimal, right: BigDecimal) -> Option<BigDecimal> {
+        if right.is_zero() {
+            return None;
+        }
+        Some(
+            exact_quotient(&left, &right)
+                .unwrap_or_else(|| (left / right).with_prec(self.options.precision + 2)),
+        )
+    }
+
+    fn pow(&self, base: BigDecimal, exponent: BigDecimal) -> Option<BigDecimal> {
+        if !exponent.is_integer() {
+            return None;
+        }
+        let exponent = exponent.to_i64()?;
+        if exponent.abs() > MAX_OPERAND
+            || written_digits(&base).saturating_mul(exponent.unsigned_abs()) > MAX_DIGITS
+        {
+            return None;
+        }
+
+        let mut result = BigDecimal::from(1);
+        let mut square = base;
+        let mut remaining = exponent.unsigned_abs();
+        while remaining > 0 {
+            if remaining & 1 == 1 {
+                result *= &square;
+            }
+            remaining >>= 1;
+            if remaining > 0 {
+                square = &square * &square;
+            }
+        }
+
+        if exponent < 0 {
+            self.divide(BigDecimal::from(1), result)
+        } else {
+            Some(result)
+        }
+    }
+}
+
+fn factorial(value: &BigDecimal) -> Option<BigDecimal> {
+    if !value.is_integer() {
+        return None;
+    }
+    let operand = value.to_i64()?;
+    if !(0..=MAX_OPERAND).contains(&operand) {
+        return None;
+    }
+
+    let digits: f64 = (2..=operand).map(|factor| (factor as f64).log10()).sum();
+    if digits > MAX_DIGITS as f64 {
+        return None;
+    }
+
+    let mut result = BigDecimal::from(1);
+    for factor in 2..=operand {
+        result *= BigDecimal::from(factor);
+    }
+    Some(result)
+}
+
+/// Divides two decimals whose quotient has a finite decimal expansion
+///
+/// # Returns
+/// The exact quotient, or None if it doesn't terminate, the divisor having prime factors
+/// other than 2 and 5 left once the dividend is accounted for
+fn exact_quotient(left: &BigDecimal, right: &BigDecimal) -> Option<BigDecimal> {
+    let (numerator, left_scale) = left.as_bigint_and_exponent();
+    let (mut rest, right_scale) = right.as_bigint_and_exponent();
+
+    let mut twos = 0;
+    while (&rest % 2u8).is_zero() {
+        rest /= 2u8;
+        twos += 1;
+    }
+    let mut fives = 0;
+    while (&rest % 5u8).is_zero() {
+        rest /= 5u8;
+        fives += 1;
+    }
+    if !(&numerator % &rest).is_zero() {
+        return None;
+    }
+
+    // a / (rest * 2^twos * 5^fives) = a / rest * 2^(n - twos) * 5^(n - fives) / 10^n
+    let digits = twos.max(fives);
+    let scaled = numerator / rest
+        * BigInt::from(2u8).pow(digits - twos)
+        * BigInt::from(5u8).pow(digits - fives);
+    Some(BigDecimal::new(
+        scaled,
+        i64::from(digits) + left_scale - right_scale,
+    ))
+}
+
+/// Counts the digits written for a value, on both sides of the decimal point
+fn written_digits(value: &BigDecimal) -> u64 {
+    let value = value.normalized();
+    let (_, scale) = value.as_bigint_and_exponent();
+    let integer_digits = (value.digits() as i64 - scale).max(1);
+    integer_digits as u64 + scale.max(0) as u64
+}
+
+fn format_decimal(value: &BigDecimal, scientific: bool) -> String {
+    let sign = if *value < BigDecimal::zero() { "-" } else { "" };
+    let (digits, scale) = value.abs().normalized().as_bigint_and_exponent();
+    let digits = digits.to_string();
+
+    if digits == "0" {
+        return "0".to_string();
+    }
+
+    if scientific {
+        let exponent = digits.len() as i64 - 1 - scale;
+        let (first, rest) = digits.split_at(1);
+        return if rest.is_empty() {
+            format!("{sign}{first}e{exponent}")
+        } else {
+            format!("{sign}{first}.{rest}e{exponent}")
+        };
+    }
+
+    if scale <= 0 {
+        format!("{sign}{digits}{}", "0".repeat(-scale as usize))
+    } else if scale as usize >= digits.len() {
+        let zeros = "0".repeat(scale as usize - digits.len());
+        format!("{sign}0.{zeros}{digits}")
+    } else {
+        let (integer, fraction) = digits.split_at(digits.len() - scale as usize);
+        format!("{sign}{integer}.{fraction}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluate(expression: &str) -> Option<String> {
+        try_evaluate(expression, &Calculator::default())
+    }
+
+    #[test]
+    fn adds_decimals_exactly() {
+        assert_eq!(evaluate("0.1 + 0.2").as_deref(), Some("0.3"));
+    }
+
+    #[test]
+    fn keeps_exact_quotients() {
+        assert_eq!(
+            evaluate("123456789012345678901234567890123456789 / 1").as_deref(),
+            Some("123456789012345678901234567890123456789")
+        );
+        assert_eq!(evaluate("1 / 1024").as_deref(), Some("0.0009765625"));
+        assert_eq!(evaluate("-7.5 / 0.25").as_deref(), Some("-30"));
+    }
+
+    #[test]
+    fn rounds_repeating_quotients_to_the_precision() {
+        assert_eq!(
+            evaluate("1 / 3").as_deref(),
+            Some("0.33333333333333333333333333333333")
+        );
+    }
+
+    #[test]
+    fn computes_remainders_exactly() {
+        assert_eq!(evaluate("10^40 % 7").as_deref(), Some("4"));
+        assert_eq!(evaluate("-7 % 3").as_deref(), Some("-1"));
+        assert_eq!(evaluate("5.5 % 2").as_deref(), Some("1.5"));
+        assert_eq!(evaluate("1 % 0"), None);
+    }
+
+    #[test]
+    fn computes_large_powers_and_factorials() {
+        assert_eq!(
+            evaluate("2^100").as_deref(),
+            Some("1267650600228229401496703205376")
+        );
+        assert_eq!(evaluate("2^-2").as_deref(), Some("0.25"));
+        assert_eq!(evaluate("20!").as_deref(), Some("2432902008176640000"));
+    }
+
+    #[test]
+    fn rejects_results_too_large_to_compute() {
+        assert_eq!(evaluate("1000!^1000"), None);
+        assert_eq!(evaluate("10^20000"), None);
+        assert_eq!(evaluate("5000!"), None);
+    }
+
+    #[test]
+    fn rejects_other_syntax() {
+        assert_eq!(evaluate("2 +"), None);
+        assert_eq!(evaluate("sqrt(2)"), None);
+        assert_eq!(evaluate("1.5!"), None);
+    }
+
+    #[test]
+    fn writes_scientific_notation() {
+        let options = Calculator {
+            scientific: true,
+            ..Calculator::default()
+        };
+        assert_eq!(try_evaluate("1500 * 2", &options).as_deref(), Some("3e3"));
+        assert_eq!(try_evaluate("1 / 8", &options).as_deref(), Some("1.25e-1"));
+    }
+}
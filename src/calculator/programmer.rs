@@ -0,0 +1,326 @@
+use crate::calculator::Evaluator;
+use crate::queryable::Queryable;
+
+/// Binary operators from the loosest to the tightest binding, following C precedence
+///
+/// `^` is a power like in the rest of the calculator, binding tighter than all of them
+/// and the unary operators, so bitwise xor is written `xor`.
+const PRECEDENCE: [&[&str]; 6] = [
+    &["|"],
+    &["xor"],
+    &["&"],
+    &["<<", ">>"],
+    &["+", "-"],
+    &["*", "/", "%"],
+];
+
+const SYMBOLS: [&str; 14] = [
+    "<<", ">>", "+", "-", "*", "/", "%", "&", "|", "xor", "^", "~", "(", ")",
+];
+
+const BITWISE_SYMBOLS: [&str; 6] = ["<<", ">>", "&", "|", "~", "xor"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Base {
+    Decimal,
+    Hexadecimal,
+    Octal,
+    Binary,
+}
+
+impl Base {
+    const ALL: [Base; 4] = [Base::Decimal, Base::Hexadecimal, Base::Octal, Base::Binary];
+
+    fn from_name(name: &str) -> Option<Base> {
+        match name {
+            "dec" | "decimal" => Some(Base::Decimal),
+            "hex" | "hexadecimal" => Some(Base::Hexadecimal),
+            "oct" | "octal" => Some(Base::Octal),
+            "bin" | "binary" => Some(Base::Binary),
+            _ => None,
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Base::Decimal => "Decimal",
+            Base::Hexadecimal => "Hexadecimal",
+            Base::Octal => "Octal",
+            Base::Binary => "Binary",
+        }
+    }
+
+    fn format(&self, value: i64) -> String {
+        match self {
+            Base::Decimal => format!("{}", value),
+            Base::Hexadecimal => format!("{:#x}", value),
+            Base::Octal => format!("{:#o}", value),
+            Base::Binary => format!("{:#b}", value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Token {
+    Number(i64),
+    Symbol(&'static str),
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+/// Attempts to evaluate an integer expression with base literals and bitwise operators
+///
+/// # Arguments
+/// * `query` - The search query, optionally ending with `in hex`, `in bin`...
+///
+/// # Returns
+/// One calculator result per base, the requested base first, or None if the
+/// query isn't a programmer expression
+pub fn try_evaluate(query: &str) -> Option<Vec<Queryable>> {
+    let query = query.trim().to_lowercase();
+    let (expression, target) = split_target_base(&query);
+
+    if target.is_none() && !is_programmer_expression(expression) {
+        return None;
+    }
+
+    let value = evaluate_expression(expression)?;
+
+    let mut bases = Vec::from(Base::ALL);
+    if let Some(target) = target {
+        bases.retain(|base| *base != target);
+        bases.insert(0, target);
+    }
+
+    Some(
+        bases
+            .into_iter()
+            .map(|base| Queryable::Calculator {
+                expression: query.clone(),
+                result: base.format(value),
+                note: Some(base.label().to_string()),
+                evaluator: Evaluator::Programmer,
+            })
+            .collect(),
+    )
+}
+
+fn split_target_base(query: &str) -> (&str, Option<Base>) {
+    if let Some((expression, name)) = query.rsplit_once(" in ")
+        && let Some(base) = Base::from_name(name.trim())
+    {
+        return (expression.trim(), Some(base));
+    }
+    (query, None)
+}
+
+fn is_programmer_expression(expression: &str) -> bool {
+    let has_based_literal = expression
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .any(|word| {
+            word.len() > 2
+                && (word.starts_with("0x") || word.starts_with("0b") || word.starts_with("0o"))
+        });
+
+    has_based_literal
+        || BITWISE_SYMBOLS
+            .iter()
+            .any(|symbol| expression.contains(symbol))
+}
+
+fn evaluate_expression(expression: &str) -> Option<i64> {
+    let mut parser = Parser {
+        tokens: tokenize(expression)?,
+        position: 0,
+    };
+
+    let value = parser.binary(0)?;
+    if parser.position != parser.tokens.len() {
+        return None;
+    }
+    Some(value)
+}
+
+fn tokenize(expression: &str) -> Option<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut rest = expression.trim_start();
+
+    while !rest.is_empty() {
+        if rest.starts_with(|c: char| c.is_ascii_digit()) {
+            let end = rest
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+            tokens.push(Token::Number(parse_literal(&rest[..end].replace('_', ""))?));
+            rest = &rest[end..];
+        } else {
+            let symbol = SYMBOLS
+                .into_iter()
+                .find(|symbol| rest.starts_with(symbol))?;
+            tokens.push(Token::Symbol(symbol));
+            rest = &rest[symbol.len()..];
+        }
+        rest = rest.trim_start();
+    }
+
+    Some(tokens)
+}
+
+fn parse_literal(literal: &str) -> Option<i64> {
+    let (digits, radix) = if let Some(digits) = literal.strip_prefix("0x") {
+        (digits, 16)
+    } else if let Some(digits) = literal.strip_prefix("0b") {
+        (digits, 2)
+    } else if let Some(digits) = literal.strip_prefix("0o") {
+        (digits, 8)
+    } else {
+        (literal, 10)
+    };
+
+    i64::from_str_radix(digits, radix).ok()
+}
+
+impl Parser {
+    fn peek_symbol(&self) -> Option<&'static str> {
+        match self.tokens.get(self.position) {
+            Some(Token::Symbol(symbol)) => Some(symbol),
+            _ => None,
+        }
+    }
+
+    fn binary(&mut self, level: usize) -> Option<i64> {
+        if level == PRECEDENCE.len() {
+            return self.unary();
+        }
+
+        let mut left = self.binary(level + 1)?;
+        while let Some(symbol) = self.peek_symbol() {
+            if !PRECEDENCE[level].contains(&symbol) {
+                break;
+            }
+            self.position += 1;
+            let right = self.binary(level + 1)?;
+            left = apply(symbol, left, right)?;
+        }
+        Some(left)
+    }
+
+    fn unary(&mut self) -> Option<i64> {
+        let symbol = self.peek_symbol();
+        if matches!(symbol, Some("-" | "+" | "~")) {
+            self.position += 1;
+        }
+
+        match symbol {
+            Some("-") => self.unary()?.checked_neg(),
+            Some("+") => self.unary(),
+            Some("~") => Some(!self.unary()?),
+            _ => self.power(),
+        }
+    }
+
+    /// Parses a power, right associative so that `2^3^2` is `2^9`
+    fn power(&mut self) -> Option<i64> {
+        let base = self.primary()?;
+        if self.peek_symbol() != Some("^") {
+            return Some(base);
+        }
+
+        self.position += 1;
+        let exponent = self.unary()?;
+        base.checked_pow(u32::try_from(exponent).ok()?)
+    }
+
+    fn primary(&mut self) -> Option<i64> {
+        let token = *self.tokens.get(self.position)?;
+        self.position += 1;
+
+        match token {
+            Token::Number(value) => Some(value),
+            Token::Symbol("(") => {
+                let value = self.binary(0)?;
+                if self.peek_symbol() != Some(")") {
+                    return None;
+                }
+                self.position += 1;
+                Some(value)
+            }
+            Token::Symbol(_) => None,
+        }
+    }
+}
+
+fn apply(symbol: &str, left: i64, right: i64) -> Option<i64> {
+    match symbol {
+        "+" => left.checked_add(right),
+        "-" => left.checked_sub(right),
+        "*" => left.checked_mul(right),
+        "/" => left.checked_div(right),
+        "%" => left.checked_rem(right),
+        "&" => Some(left & right),
+        "|" => Some(left | right),
+        "xor" => Some(left ^ right),
+        "<<" => left.checked_shl(u32::try_from(right).ok()?),
+        ">>" => left.checked_shr(u32::try_from(right).ok()?),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_base_literals() {
+        assert_eq!(evaluate_expression("0xff + 0b1010"), Some(265));
+        assert_eq!(evaluate_expression("0o17 + 1_000"), Some(1015));
+        assert_eq!(evaluate_expression("0xzz"), None);
+    }
+
+    #[test]
+    fn follows_c_precedence() {
+        assert_eq!(evaluate_expression("1 << 2 + 1"), Some(8));
+        assert_eq!(evaluate_expression("6 & 3 | 8"), Some(10));
+        assert_eq!(evaluate_expression("(1 + 2) * 3"), Some(9));
+        assert_eq!(evaluate_expression("~0"), Some(-1));
+    }
+
+    #[test]
+    fn caret_is_a_power_and_xor_is_spelled_out() {
+        assert_eq!(evaluate_expression("0x10 ^ 2"), Some(256));
+        assert_eq!(evaluate_expression("2 ^ 3 ^ 2"), Some(512));
+        assert_eq!(evaluate_expression("-2 ^ 2"), Some(-4));
+        assert_eq!(evaluate_expression("2 ^ -1"), None);
+        assert_eq!(evaluate_expression("6 xor 3"), Some(5));
+        assert_eq!(evaluate_expression("1 | 6 xor 3 & 1"), Some(7));
+    }
+
+    #[test]
+    fn rejects_overflow_and_division_by_zero() {
+        assert_eq!(evaluate_expression("2 ^ 64"), None);
+        assert_eq!(evaluate_expression("1 / 0"), None);
+        assert_eq!(evaluate_expression("1 << 64"), None);
+    }
+
+    #[test]
+    fn detects_programmer_expressions() {
+        assert!(is_programmer_expression("0x1f + 1"));
+        assert!(is_programmer_expression("6 xor 3"));
+        assert!(is_programmer_expression("1 << 4"));
+        assert!(!is_programmer_expression("2 ^ 10"));
+        assert!(!is_programmer_expression("0x"));
+    }
+
+    #[test]
+    fn lists_the_requested_base_first() {
+        let results = try_evaluate("255 in hex").unwrap();
+        let Queryable::Calculator { result, note, .. } = &results[0] else {
+            panic!("expected a calculator result");
+        };
+        assert_eq!(result, "0xff");
+        assert_eq!(note.as_deref(), Some("Hexadecimal"));
+        assert_eq!(results.len(), 4);
+    }
+}
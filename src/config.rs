@@ -3,15 +3,81 @@ use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 
+use crate::template;
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct CustomCommand {
     pub name: String,
-    pub command: String,
+    pub command: CommandTemplate,
     pub description: Option<String>,
     #[serde(default)]
     pub accepts_arguments: bool,
     #[serde(default)]
     pub tty: bool,
+    #[serde(default)]
+    pub shell: bool,
+    #[serde(default)]
+    pub parameters: Vec<Parameter>,
+    pub completion: Option<Completion>,
+    #[serde(default)]
+    pub confirm: bool,
+    pub confirm_message: Option<String>,
+    #[serde(default)]
+    pub output: OutputMode,
+}
+
+/// What happens to the output of a custom command
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputMode {
+    /// The command runs detached and its output is discarded
+    #[default]
+    Detached,
+    /// The output lines are shown as results in the launcher
+    Inline,
+}
+
+/// Where the argument suggestions of a custom command come from
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Completion {
+    /// A fixed list of candidates
+    Values(Vec<String>),
+    /// The paths matching a pattern like `~/notes/*.md`
+    Glob(String),
+    /// The output lines of a shell command
+    Command(String),
+}
+
+/// A named value asked for before running a custom command, used as `{name}` in it
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Parameter {
+    pub name: String,
+    #[serde(default, rename = "type")]
+    pub kind: ParameterKind,
+    #[serde(default)]
+    pub choices: Vec<String>,
+    pub default: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ParameterKind {
+    #[default]
+    String,
+    Path,
+    Number,
+    Choice,
+}
+
+/// A command written either as a single line or as a list of argv words
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum CommandTemplate {
+    Line(String),
+    Argv(Vec<String>),
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -20,10 +86,143 @@ pub struct SearchUrl {
     pub url: String,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Currency {
+    pub rates_file: Option<String>,
+    pub update_command: Option<String>,
+}
+
+/// How launched programs are started
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum LaunchStrategy {
+    /// As a child of the launcher
+    #[default]
+    Direct,
+    /// In a new session, detached from the launcher
+    Setsid,
+    /// In a transient `systemd-run --user --scope` unit named after the desktop file ID
+    SystemdScope,
+}
+
+/// Which GNOME Shell search providers of installed apps are asked for results
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SearchProviders {
+    #[serde(default = "default_search_providers_enabled")]
+    pub enabled: bool,
+    /// Desktop file IDs of the apps whose providers are skipped
+    #[serde(default)]
+    pub disabled: Vec<String>,
+}
+
+/// Which KRunner D-Bus runners are asked for matches
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct KRunner {
+    #[serde(default = "default_krunner_enabled")]
+    pub enabled: bool,
+    /// Plugin IDs of the runners that are skipped
+    #[serde(default)]
+    pub disabled: Vec<String>,
+}
+
+/// The pane showing the details of the selected result
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Preview {
+    /// Whether the pane is shown when the launcher opens, it can be toggled either way
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub position: PreviewPosition,
+}
+
+/// Where the preview pane is placed, relative to the result list
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum PreviewPosition {
+    #[default]
+    Right,
+    Bottom,
+}
+
+/// Which keys trigger the launcher actions
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct KeyBindings {
+    /// The bindings to start from
+    #[serde(default)]
+    pub preset: KeyPreset,
+    /// Accelerators like `<Control>n`, replacing the preset bindings of their action
+    #[serde(flatten)]
+    pub actions: HashMap<KeyAction, Vec<String>>,
+}
+
+/// Sets of key bindings shipped with the launcher
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeyPreset {
+    #[default]
+    Default,
+    /// Ctrl+N/P to move, Alt+</Alt+> to go to the ends, Ctrl+G to close
+    Emacs,
+    /// Ctrl+J/K to move, Ctrl+D/U to page, Ctrl+[ to close
+    Vim,
+}
+
+/// What a key binding does
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeyAction {
+    Next,
+    Previous,
+    PageDown,
+    PageUp,
+    First,
+    Last,
+    Activate,
+    AlternateAction,
+    Complete,
+    Clear,
+    Close,
+    TogglePreview,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Calculator {
+    #[serde(default = "default_exact")]
+    pub exact: bool,
+    #[serde(default = "default_precision")]
+    pub precision: u64,
+    #[serde(default)]
+    pub scientific: bool,
+    #[serde(default)]
+    pub degrees: bool,
+}
+
 pub type SearchUrls = HashMap<String, SearchUrl>;
 
 pub type CustomCommands = HashMap<String, CustomCommand>;
 
+/// A script answering queries with result rows, like a rofi script
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ScriptProvider {
+    pub name: String,
+    pub command: String,
+    /// Only queries starting with this word are sent to the script
+    pub prefix: Option<String>,
+}
+
+pub type ScriptProviders = HashMap<String, ScriptProvider>;
+
+/// A long-running plugin answering JSON-RPC requests on its standard input
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Plugin {
+    pub name: String,
+    pub command: String,
+    /// Only queries starting with this word are sent to the plugin
+    pub prefix: Option<String>,
+}
+
+pub type Plugins = HashMap<String, Plugin>;
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Config {
     #[serde(default = "default_width")]
@@ -36,10 +235,33 @@ pub struct Config {
     pub search_urls: SearchUrls,
     #[serde(default = "default_terminal")]
     pub terminal: String,
+    /// Opens files for editing, `$VISUAL` or `$EDITOR` in the terminal when unset
+    #[serde(default)]
+    pub editor: Option<String>,
+    #[serde(default = "default_private_browser")]
+    pub private_browser: String,
+    #[serde(default)]
+    pub launch_strategy: LaunchStrategy,
     #[serde(default)]
     pub custom_commands: CustomCommands,
     #[serde(default)]
+    pub scripts: ScriptProviders,
+    #[serde(default)]
+    pub plugins: Plugins,
+    #[serde(default)]
+    pub search_providers: SearchProviders,
+    #[serde(default)]
+    pub krunner: KRunner,
+    #[serde(default)]
+    pub preview: Preview,
+    #[serde(default)]
+    pub keybindings: KeyBindings,
+    #[serde(default)]
     pub extra_paths: Vec<String>,
+    #[serde(default)]
+    pub currency: Currency,
+    #[serde(default)]
+    pub calculator: Calculator,
 }
 
 fn default_height() -> i32 {
@@ -64,6 +286,9 @@ fn default_search_urls() -> SearchUrls {
 fn default_terminal() -> String {
     "alacritty -e".to_string()
 }
+fn default_private_browser() -> String {
+    "firefox --private-window".to_string()
+}
 
 impl Default for Config {
     fn default() -> Self {
@@ -73,8 +298,63 @@ impl Default for Config {
             placeholder: default_placeholder(),
             search_urls: default_search_urls(),
             terminal: default_terminal(),
+            editor: None,
+            private_browser: default_private_browser(),
+            launch_strategy: LaunchStrategy::default(),
             custom_commands: HashMap::new(),
+            scripts: HashMap::new(),
+            plugins: HashMap::new(),
+            search_providers: SearchProviders::default(),
+            krunner: KRunner::default(),
+            preview: Preview::default(),
+            keybindings: KeyBindings::default(),
             extra_paths: Vec::new(),
+            currency: Currency::default(),
+            calculator: Calculator::default(),
+        }
+    }
+}
+
+fn default_search_providers_enabled() -> bool {
+    true
+}
+
+impl Default for SearchProviders {
+    fn default() -> Self {
+        Self {
+            enabled: default_search_providers_enabled(),
+            disabled: Vec::new(),
+        }
+    }
+}
+
+fn default_krunner_enabled() -> bool {
+    true
+}
+
+impl Default for KRunner {
+    fn default() -> Self {
+        Self {
+            enabled: default_krunner_enabled(),
+            disabled: Vec::new(),
+        }
+    }
+}
+
+fn default_exact() -> bool {
+    true
+}
+fn default_precision() -> u64 {
+    32
+}
+
+impl Default for Calculator {
+    fn default() -> Self {
+        Self {
+            exact: default_exact(),
+            precision: default_precision(),
+            scientific: false,
+            degrees: false,
         }
     }
 }
@@ -100,3 +380,25 @@ impl SearchUrl {
         self.url.replace("{q}", &encoded_query)
     }
 }
+
+impl CommandTemplate {
+    /// Returns the argv words of the command, placeholders included
+    pub fn words(&self) -> Result<Vec<String>, String> {
+        match self {
+            CommandTemplate::Line(line) => template::split(line),
+            CommandTemplate::Argv(words) => Ok(words.clone()),
+        }
+    }
+
+    /// Returns the command as a single shell command line
+    pub fn line(&self) -> String {
+        match self {
+            CommandTemplate::Line(line) => line.clone(),
+            CommandTemplate::Argv(words) => words
+                .iter()
+                .map(|word| template::quote(word))
+                .collect::<Vec<_>>()
+                .join(" "),
+        }
+    }
+}
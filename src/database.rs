@@ -0,0 +1,47 @@
+use log::error;
+use rusqlite::Connection;
+use std::fs;
+use std::path::PathBuf;
+
+const SCHEMA: &str = r#"
+    CREATE TABLE IF NOT EXISTS calculator_variables (
+        name TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS calculator_history (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        expression TEXT NOT NULL,
+        result TEXT NOT NULL,
+        created_at INTEGER NOT NULL
+    );
+"#;
+
+fn get_database_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("palet").join("palet.db"))
+}
+
+/// Opens the local database, creating it and its tables if needed
+///
+/// # Returns
+/// The connection, or None if the database can't be opened
+pub fn open() -> Option<Connection> {
+    let path = get_database_path()?;
+    if let Some(parent) = path.parent()
+        && let Err(error) = fs::create_dir_all(parent)
+    {
+        error!("Unable to create {}: {error}", parent.display());
+        return None;
+    }
+
+    let connection = Connection::open(&path)
+        .map_err(|error| error!("Unable to open {}: {error}", path.display()))
+        .ok()?;
+
+    connection
+        .execute_batch(SCHEMA)
+        .map_err(|error| error!("Unable to migrate {}: {error}", path.display()))
+        .ok()?;
+
+    Some(connection)
+}
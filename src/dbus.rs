@@ -0,0 +1,94 @@
+use gtk4::prelude::*;
+use gtk4::{gio, glib};
+use log::error;
+use std::sync::OnceLock;
+use std::thread;
+
+static CONNECTION: OnceLock<Option<gio::DBusConnection>> = OnceLock::new();
+
+/// A D-Bus object answering calls on the session bus
+pub struct Service<'a> {
+    pub bus_name: &'a str,
+    pub object_path: &'a str,
+    pub interface: &'a str,
+}
+
+/// Returns the session bus connection, opened on first use
+pub fn session_bus() -> Option<gio::DBusConnection> {
+    CONNECTION
+        .get_or_init(|| {
+            gio::bus_get_sync(gio::BusType::Session, gio::Cancellable::NONE)
+                .inspect_err(|error| error!("Unable to connect to the session bus: {error}"))
+                .ok()
+        })
+        .clone()
+}
+
+/// Makes a call to each of several services at once, each on its own thread, so a slow
+/// service doesn't hold up the others
+///
+/// # Arguments
+/// * `services` - The services to call
+/// * `call` - Makes the calls to one service
+///
+/// # Returns
+/// The result of the calls to each service, in the order of `services`
+pub fn call_each<S: Sync, T: Send>(
+    services: &[S],
+    call: impl Fn(&S) -> Result<T, String> + Sync,
+) -> Vec<Result<T, String>> {
+    thread::scope(|scope| {
+        let handles: Vec<_> = services
+            .iter()
+            .map(|service| scope.spawn(|| call(service)))
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|_| Err("A D-Bus call panicked".to_string()))
+            })
+            .collect()
+    })
+}
+
+/// Calls a method of a service on the session bus and waits for the reply
+///
+/// # Arguments
+/// * `service` - The service to call
+/// * `method` - The method name
+/// * `parameters` - The method arguments, as a tuple variant
+/// * `timeout_ms` - How long to wait for the reply, in milliseconds
+/// * `cancellable` - Cancels the call when the query it answers is outdated
+///
+/// # Returns
+/// The reply, or an error message if the call failed, timed out or returned another type
+pub fn call<T: FromVariant + StaticVariantType>(
+    service: &Service,
+    method: &str,
+    parameters: glib::Variant,
+    timeout_ms: i32,
+    cancellable: Option<&gio::Cancellable>,
+) -> Result<T, String> {
+    let connection = session_bus().ok_or("Session bus not available")?;
+
+    let reply = connection
+        .call_sync(
+            Some(service.bus_name),
+            service.object_path,
+            service.interface,
+            method,
+            Some(&parameters),
+            Some(&T::static_variant_type()),
+            gio::DBusCallFlags::NONE,
+            timeout_ms,
+            cancellable,
+        )
+        .map_err(|error| format!("{} {method} failed: {error}", service.bus_name))?;
+
+    reply
+        .get::<T>()
+        .ok_or_else(|| format!("{} {method} returned {}", service.bus_name, reply.type_()))
+}
@@ -0,0 +1,200 @@
+use gtk4::gdk::{Key, ModifierType};
+use log::warn;
+
+use crate::config::{KeyAction, KeyBindings, KeyPreset};
+
+/// Bindings shared by every preset, as GTK accelerators
+const DEFAULT_BINDINGS: &[(KeyAction, &[&str])] = &[
+    (KeyAction::Next, &["Down"]),
+    (KeyAction::Previous, &["Up"]),
+    (KeyAction::PageDown, &["Page_Down"]),
+    (KeyAction::PageUp, &["Page_Up"]),
+    (KeyAction::First, &["<Control>Home"]),
+    (KeyAction::Last, &["<Control>End"]),
+    (KeyAction::Activate, &["Return", "KP_Enter"]),
+    (
+        KeyAction::AlternateAction,
+        &["<Alt>Return", "<Alt>KP_Enter"],
+    ),
+    (KeyAction::Complete, &["Tab"]),
+    (KeyAction::Clear, &["<Control>l"]),
+    (KeyAction::Close, &["Escape"]),
+    (KeyAction::TogglePreview, &["<Alt>p"]),
+];
+
+/// Ctrl+V and Alt+V are left out, Ctrl+V pasting in the entry
+const EMACS_BINDINGS: &[(KeyAction, &[&str])] = &[
+    (KeyAction::Next, &["<Control>n"]),
+    (KeyAction::Previous, &["<Control>p"]),
+    (KeyAction::First, &["<Alt>less"]),
+    (KeyAction::Last, &["<Alt>greater"]),
+    (KeyAction::Close, &["<Control>g"]),
+];
+
+const VIM_BINDINGS: &[(KeyAction, &[&str])] = &[
+    (KeyAction::Next, &["<Control>j"]),
+    (KeyAction::Previous, &["<Control>k"]),
+    (KeyAction::PageDown, &["<Control>d"]),
+    (KeyAction::PageUp, &["<Control>u"]),
+    (KeyAction::Close, &["<Control>bracketleft"]),
+];
+
+/// The modifiers telling key bindings apart, the mask GTK accelerators use
+const MODIFIERS: ModifierType = ModifierType::CONTROL_MASK
+    .union(ModifierType::SHIFT_MASK)
+    .union(ModifierType::ALT_MASK)
+    .union(ModifierType::SUPER_MASK)
+    .union(ModifierType::HYPER_MASK)
+    .union(ModifierType::META_MASK);
+
+/// The key bindings in effect, resolved from the configuration
+pub struct Keymap {
+    bindings: Vec<(Key, ModifierType, KeyAction)>,
+}
+
+impl Keymap {
+    /// Resolves the configured bindings
+    ///
+    /// The preset bindings come on top of the default ones, and the actions listed in
+    /// the configuration replace the bindings of both. Configured keys win over the
+    /// preset and default bindings of other actions.
+    ///
+    /// # Arguments
+    /// * `config` - The `[keybindings]` table
+    pub fn new(config: &KeyBindings) -> Keymap {
+        let preset = match config.preset {
+            KeyPreset::Default => &[],
+            KeyPreset::Emacs => EMACS_BINDINGS,
+            KeyPreset::Vim => VIM_BINDINGS,
+        };
+
+        let mut bindings = Vec::new();
+        for (action, accelerators) in &config.actions {
+            for accelerator in accelerators {
+                bindings.extend(parse(accelerator, *action));
+            }
+        }
+        for (action, accelerators) in preset.iter().chain(DEFAULT_BINDINGS) {
+            if config.actions.contains_key(action) {
+                continue;
+            }
+            for accelerator in accelerators.iter() {
+                bindings.extend(parse(accelerator, *action));
+            }
+        }
+
+        Keymap { bindings }
+    }
+
+    /// Returns the action bound to a key press
+    ///
+    /// # Arguments
+    /// * `key` - The pressed key
+    /// * `modifiers` - The modifiers held
+    ///
+    /// # Returns
+    /// The first action bound to the key, configured bindings winning over the preset
+    /// ones and preset bindings over the default ones
+    pub fn lookup(&self, key: Key, modifiers: ModifierType) -> Option<KeyAction> {
+        let (key, modifiers) = normalize(key, modifiers);
+        self.bindings
+            .iter()
+            .find(|(bound_key, bound_modifiers, _)| {
+                *bound_key == key && *bound_modifiers == modifiers
+            })
+            .map(|(_, _, action)| *action)
+    }
+}
+
+/// Returns the result Ctrl+1 to Ctrl+9 picks
+///
+/// # Arguments
+/// * `key` - The pressed key
+/// * `modifiers` - The modifiers held
+///
+/// # Returns
+/// The position of the picked result, from 0, or None for other keys
+pub fn picked_position(key: Key, modifiers: ModifierType) -> Option<u32> {
+    let (key, modifiers) = normalize(key, modifiers);
+    if modifiers != ModifierType::CONTROL_MASK {
+        return None;
+    }
+
+    key.to_unicode()
+        .and_then(|character| character.to_digit(10))
+        .filter(|digit| *digit > 0)
+        .map(|digit| digit - 1)
+}
+
+fn parse(accelerator: &str, action: KeyAction) -> Option<(Key, ModifierType, KeyAction)> {
+    let Some((key, modifiers)) = gtk4::accelerator_parse(accelerator) else {
+        warn!("Invalid key binding {accelerator} for {action:?}");
+        return None;
+    };
+
+    let (key, modifiers) = normalize(key, modifiers);
+    Some((key, modifiers, action))
+}
+
+/// Makes a key press comparable with a binding: letters are lowercased, and Shift only
+/// counts for them since other symbols like `<` may need it to be typed at all
+fn normalize(key: Key, modifiers: ModifierType) -> (Key, ModifierType) {
+    let mut modifiers = modifiers & MODIFIERS;
+    let key = key.to_lower();
+    if key.to_upper() == key {
+        modifiers.remove(ModifierType::SHIFT_MASK);
+    }
+
+    (key, modifiers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ignores_lock_modifiers() {
+        assert_eq!(
+            normalize(Key::N, ModifierType::CONTROL_MASK | ModifierType::LOCK_MASK),
+            (Key::n, ModifierType::CONTROL_MASK)
+        );
+    }
+
+    #[test]
+    fn keeps_shift_for_letters_only() {
+        let control_shift = ModifierType::CONTROL_MASK | ModifierType::SHIFT_MASK;
+        assert_eq!(normalize(Key::A, control_shift), (Key::a, control_shift));
+        assert_eq!(
+            normalize(Key::less, ModifierType::ALT_MASK | ModifierType::SHIFT_MASK),
+            (Key::less, ModifierType::ALT_MASK)
+        );
+    }
+
+    #[test]
+    fn looks_up_the_first_binding() {
+        let keymap = Keymap {
+            bindings: vec![
+                (Key::n, ModifierType::CONTROL_MASK, KeyAction::Next),
+                (Key::n, ModifierType::CONTROL_MASK, KeyAction::Previous),
+            ],
+        };
+        let caps_lock = ModifierType::CONTROL_MASK | ModifierType::LOCK_MASK;
+
+        assert_eq!(keymap.lookup(Key::N, caps_lock), Some(KeyAction::Next));
+        assert_eq!(keymap.lookup(Key::n, ModifierType::ALT_MASK), None);
+    }
+
+    #[test]
+    fn picks_results_with_control_and_a_digit() {
+        assert_eq!(
+            picked_position(Key::_3, ModifierType::CONTROL_MASK),
+            Some(2)
+        );
+        assert_eq!(picked_position(Key::_0, ModifierType::CONTROL_MASK), None);
+        assert_eq!(
+            picked_position(Key::_1, ModifierType::CONTROL_MASK | ModifierType::ALT_MASK),
+            None
+        );
+        assert_eq!(picked_position(Key::_1, ModifierType::empty()), None);
+    }
+}
@@ -0,0 +1,290 @@
+use gtk4::prelude::*;
+use gtk4::{gio, glib};
+use log::{debug, error, info};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::config::KRunner;
+use crate::dbus::{self, Service};
+use crate::queryable::{Outcome, Queryable, Task};
+
+const INTERFACE: &str = "org.kde.krunner1";
+
+/// How long a runner may take to answer a call, in milliseconds
+const QUERY_TIMEOUT_MS: i32 = 500;
+const RUN_TIMEOUT_MS: i32 = 5000;
+
+/// How many matches of each runner are shown
+const MAX_MATCHES: usize = 5;
+
+/// D-Bus runners installed for KRunner, discovered once per launcher session
+static RUNNERS: OnceLock<Vec<Runner>> = OnceLock::new();
+
+/// A D-Bus runner declared in a `krunner/dbusplugins` metadata file
+#[derive(Debug, Clone)]
+pub struct Runner {
+    pub id: String,
+    pub name: String,
+    pub service: String,
+    pub path: String,
+    /// Shorter queries aren't sent to the runner
+    pub min_letter_count: usize,
+}
+
+/// A match returned by a runner
+#[derive(Debug, Clone)]
+pub struct RunnerMatch {
+    pub runner: Runner,
+    pub id: String,
+    pub text: String,
+    pub subtext: Option<String>,
+    pub icon: Option<String>,
+    /// The KRunner match type, from 0 (no match) to 100 (exact match)
+    pub kind: i32,
+    pub relevance: f64,
+}
+
+/// The metadata of a KF6 runner, written as JSON
+#[derive(Deserialize)]
+struct JsonMetadata {
+    #[serde(rename = "KPlugin", default)]
+    plugin: JsonPluginInfo,
+    #[serde(rename = "X-Plasma-DBusRunner-Service")]
+    service: String,
+    #[serde(rename = "X-Plasma-DBusRunner-Path")]
+    path: String,
+    #[serde(rename = "X-Plasma-Runner-Min-Letter-Count")]
+    min_letter_count: Option<Value>,
+}
+
+#[derive(Deserialize, Default)]
+struct JsonPluginInfo {
+    #[serde(rename = "Id")]
+    id: Option<String>,
+    #[serde(rename = "Name")]
+    name: Option<String>,
+    #[serde(rename = "EnabledByDefault")]
+    enabled_by_default: Option<bool>,
+}
+
+/// A match as returned by `Match`: ID, text, icon name, type, relevance and properties
+type MatchTuple = (
+    String,
+    String,
+    String,
+    i32,
+    f64,
+    HashMap<String, glib::Variant>,
+);
+
+/// Asks the installed KRunner D-Bus runners for matches
+///
+/// # Arguments
+/// * `settings` - Which runners are enabled
+/// * `query` - The search query
+/// * `cancellable` - Cancels the pending calls when the query is outdated
+///
+/// # Returns
+/// The best matches of every runner answering in time, best first, all runners being
+/// asked at once
+pub fn query(settings: &KRunner, query: &str, cancellable: &gio::Cancellable) -> Vec<Queryable> {
+    let query = query.trim();
+    if !settings.enabled || query.is_empty() || dbus::session_bus().is_none() {
+        return Vec::new();
+    }
+
+    let runners: Vec<&Runner> = runners()
+        .iter()
+        .filter(|runner| {
+            !settings.disabled.contains(&runner.id)
+                && query.chars().count() >= runner.min_letter_count
+        })
+        .collect();
+    let answers = dbus::call_each(&runners, |runner| {
+        dbus::call::<(Vec<MatchTuple>,)>(
+            &runner.service(),
+            "Match",
+            (query,).to_variant(),
+            QUERY_TIMEOUT_MS,
+            Some(cancellable),
+        )
+    });
+
+    let mut matches: Vec<RunnerMatch> = Vec::new();
+    for (runner, answer) in runners.into_iter().zip(answers) {
+        match answer {
+            Ok((mut runner_matches,)) => {
+                runner_matches.sort_by(|a, b| b.3.cmp(&a.3).then(b.4.total_cmp(&a.4)));
+                runner_matches.truncate(MAX_MATCHES);
+                matches.extend(
+                    runner_matches
+                        .into_iter()
+                        .map(|runner_match| parse_match(runner, runner_match)),
+                );
+            }
+            Err(message) => debug!("{message}"),
+        }
+    }
+
+    matches.sort_by(|a, b| {
+        b.kind
+            .cmp(&a.kind)
+            .then(b.relevance.total_cmp(&a.relevance))
+    });
+    matches.into_iter().map(Queryable::RunnerMatch).collect()
+}
+
+/// Runs a match in the runner that returned it
+///
+/// # Arguments
+/// * `runner_match` - The match to run
+///
+/// # Returns
+/// A task calling the runner off the main thread, the launcher closing once it answers
+pub fn run(runner_match: &RunnerMatch) -> Result<Outcome, String> {
+    let runner_match = runner_match.clone();
+
+    Ok(Outcome::Pending(Task::new(
+        &runner_match.text.clone(),
+        move |cancellable| {
+            dbus::call::<()>(
+                &runner_match.runner.service(),
+                "Run",
+                (runner_match.id.as_str(), "").to_variant(),
+                RUN_TIMEOUT_MS,
+                Some(cancellable),
+            )
+            .map(|()| Outcome::Close)
+        },
+    )))
+}
+
+impl Runner {
+    fn service(&self) -> Service<'_> {
+        Service {
+            bus_name: &self.service,
+            object_path: &self.path,
+            interface: INTERFACE,
+        }
+    }
+}
+
+fn parse_match(runner: &Runner, runner_match: MatchTuple) -> RunnerMatch {
+    let (id, text, icon, kind, relevance, properties) = runner_match;
+
+    RunnerMatch {
+        runner: runner.clone(),
+        id,
+        text,
+        subtext: properties
+            .get("subtext")
+            .and_then(|subtext| subtext.get::<String>())
+            .filter(|subtext| !subtext.is_empty()),
+        icon: Some(icon).filter(|icon| !icon.is_empty()),
+        kind,
+        relevance,
+    }
+}
+
+fn runners() -> &'static [Runner] {
+    RUNNERS.get_or_init(discover_runners)
+}
+
+/// Reads the runner metadata files of every data directory, the user's first
+fn discover_runners() -> Vec<Runner> {
+    let mut data_dirs = vec![glib::user_data_dir()];
+    data_dirs.extend(glib::system_data_dirs());
+
+    let mut runners: Vec<Runner> = Vec::new();
+    for data_dir in data_dirs {
+        let Ok(entries) = fs::read_dir(data_dir.join("krunner/dbusplugins")) else {
+            continue;
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let runner = match path.extension().and_then(|extension| extension.to_str()) {
+                Some("json") => parse_json_metadata(&path),
+                Some("desktop") => parse_desktop_metadata(&path),
+                _ => continue,
+            };
+
+            let Some(runner) = runner else {
+                continue;
+            };
+            if runner.service.ends_with('*') {
+                debug!("{} runs as several instances, skipping it", runner.id);
+            } else if !runners.iter().any(|known| known.id == runner.id) {
+                runners.push(runner);
+            }
+        }
+    }
+
+    info!("Found {} KRunner runners", runners.len());
+    runners
+}
+
+fn parse_json_metadata(path: &Path) -> Option<Runner> {
+    let metadata = fs::read_to_string(path)
+        .map_err(|error| error.to_string())
+        .and_then(|content| {
+            serde_json::from_str::<JsonMetadata>(&content).map_err(|error| error.to_string())
+        })
+        .inspect_err(|error| error!("Unable to read {}: {error}", path.display()))
+        .ok()?;
+
+    if metadata.plugin.enabled_by_default == Some(false) {
+        debug!("{} is disabled by default", path.display());
+        return None;
+    }
+
+    let id = metadata
+        .plugin
+        .id
+        .or_else(|| Some(path.file_stem()?.to_str()?.to_string()))?;
+    let min_letter_count = match metadata.min_letter_count {
+        Some(Value::Number(count)) => count.as_u64(),
+        Some(Value::String(count)) => count.parse().ok(),
+        _ => None,
+    };
+
+    Some(Runner {
+        name: metadata.plugin.name.unwrap_or_else(|| id.clone()),
+        id,
+        service: metadata.service,
+        path: metadata.path,
+        min_letter_count: min_letter_count.unwrap_or_default() as usize,
+    })
+}
+
+fn parse_desktop_metadata(path: &Path) -> Option<Runner> {
+    let entry = freedesktop_entry_parser::parse_entry(path)
+        .inspect_err(|error| error!("Unable to read {}: {error}", path.display()))
+        .ok()?;
+    let section = entry.section("Desktop Entry");
+
+    if section.attr("X-KDE-PluginInfo-EnabledByDefault") == Some("false") {
+        debug!("{} is disabled by default", path.display());
+        return None;
+    }
+
+    let id = section
+        .attr("X-KDE-PluginInfo-Name")
+        .or_else(|| path.file_stem()?.to_str())?
+        .to_string();
+
+    Some(Runner {
+        name: section.attr("Name").unwrap_or(&id).to_string(),
+        service: section.attr("X-Plasma-DBusRunner-Service")?.to_string(),
+        path: section.attr("X-Plasma-DBusRunner-Path")?.to_string(),
+        min_letter_count: section
+            .attr("X-Plasma-Runner-Min-Letter-Count")
+            .and_then(|count| count.parse().ok())
+            .unwrap_or_default(),
+        id,
+    })
+}
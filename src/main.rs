@@ -1,22 +1,64 @@
 use gtk4::prelude::*;
 use gtk4::{
-    Application, ApplicationWindow, Box, Entry, EventControllerKey, Label, ListBox, ListBoxRow,
-    Orientation, ScrolledWindow, glib,
+    Application, ApplicationWindow, Box, Entry, EventControllerKey, GestureClick, Image, Label,
+    ListItem, ListView, Orientation, ScrolledWindow, SignalListItemFactory, SingleSelection, gio,
+    glib,
 };
-use log::LevelFilter;
+use log::{LevelFilter, error, info};
+use std::cell::RefCell;
 use std::env;
+use std::rc::Rc;
+use std::sync::Arc;
 
 mod application;
+mod calculator;
 mod config;
 mod database;
+mod dbus;
+mod keybindings;
+mod krunner;
+mod plugin;
+mod preview;
 mod queryable;
+mod script;
+mod search;
+mod search_provider;
 mod style;
+mod template;
 
 const APP_ID: &str = "com.example.palet";
 
+/// What the list displays instead of the search results
+#[derive(Default)]
+struct OverlayState {
+    /// Whether the list shows other items than the search results, like the secondary
+    /// actions of a result
+    displayed: bool,
+    /// The custom command parameter the entry text currently answers
+    prompt: Option<queryable::Prompt>,
+    /// Cancels the task whose outcome the list waits for
+    task: Option<gio::Cancellable>,
+}
+
+type Overlay = Rc<RefCell<OverlayState>>;
+
+/// The results of the current query, completed as the providers answer
+#[derive(Default)]
+struct SearchState {
+    results: search::Results,
+    /// Stops the providers still working on the query once it changes
+    cancellable: Option<gio::Cancellable>,
+}
+
+type Search = Rc<RefCell<SearchState>>;
+
 /// Entry point for the application
 fn main() -> glib::ExitCode {
     configure_logger();
+    if env::args().any(|arg| arg == "--update-rates") {
+        return update_rates();
+    }
+
     let app = Application::builder().application_id(APP_ID).build();
     app.connect_activate(build_ui);
     app.run_with_args(&[] as &[String])
@@ -35,6 +77,24 @@ fn configure_logger() {
     log::set_max_level(level);
 }
 
+/// Refreshes the currency rates file using the configured update command
+///
+/// # Returns
+/// Success if the rates file was written
+fn update_rates() -> glib::ExitCode {
+    match calculator::update_rates(&config::load_config().currency) {
+        Ok(path) => {
+            let rates_file = path.display();
+            info!("Rates written to {rates_file}");
+            glib::ExitCode::SUCCESS
+        }
+        Err(message) => {
+            error!("{message}");
+            glib::ExitCode::FAILURE
+        }
+    }
+}
+
 /// Creates the text entry widget
 ///
 /// # Arguments
@@ -51,23 +111,90 @@ fn create_entry(config: &config::Config) -> Entry {
 
 /// Creates the application list widget
 ///
+/// Only the visible rows are created, and they are reused for other items as the list
+/// scrolls or changes. The model holds the results of the current query in the order
+/// they were built, replaced as a whole when it changes: there is no filter or sort model
+/// on top of it since most rows, like calculations or provider results, only exist for
+/// the query making them. Applications aren't ranked, they are kept when their name or
+/// description contains the query and listed alphabetically.
+///
+/// # Arguments
+/// * `model` - The items to display
+///
 /// # Returns
-/// The configured ListBox widget
-fn create_list_box() -> ListBox {
-    ListBox::builder().css_classes(["app-list"]).build()
+/// The configured ListView widget
+fn create_list_view(model: &gio::ListStore) -> ListView {
+    let selection = SingleSelection::builder()
+        .model(model)
+        .autoselect(true)
+        .can_unselect(false)
+        .build();
+    let factory = create_item_factory(&selection);
+
+    ListView::builder()
+        .model(&selection)
+        .factory(&factory)
+        .css_classes(["app-list"])
+        .build()
+}
+
+/// Creates the factory building and filling the list rows
+///
+/// # Arguments
+/// * `selection` - The selection of the list, pressing a row selects it
+///
+/// # Returns
+/// The configured SignalListItemFactory
+fn create_item_factory(selection: &SingleSelection) -> SignalListItemFactory {
+    let factory = SignalListItemFactory::new();
+    let selection = selection.clone();
+
+    factory.connect_setup(move |_, object| {
+        let Some(list_item) = object.downcast_ref::<ListItem>() else {
+            return;
+        };
+        let row = create_item_widget();
+
+        // Select the row before a click is handled, whatever the button
+        let press = GestureClick::builder().button(0).build();
+        let list_item_weak = list_item.downgrade();
+        let selection_clone = selection.clone();
+        press.connect_pressed(move |_, _, _, _| {
+            if let Some(list_item) = list_item_weak.upgrade() {
+                selection_clone.set_selected(list_item.position());
+            }
+        });
+        row.add_controller(press);
+
+        list_item.set_child(Some(&row));
+    });
+
+    factory.connect_bind(|_, object| {
+        let Some(list_item) = object.downcast_ref::<ListItem>() else {
+            return;
+        };
+        let row = list_item.child().and_downcast::<Box>();
+        let item = list_item.item().and_downcast::<glib::BoxedAnyObject>();
+
+        if let (Some(row), Some(item)) = (row, item) {
+            fill_item_widget(&row, &item.borrow::<queryable::Queryable>());
+        }
+    });
+
+    factory
 }
 
 /// Creates the scrolled window containing the application list
 ///
 /// # Arguments
-/// * `list_box` - The list box to wrap
+/// * `list_view` - The list view to wrap
 /// * `config` - Application configuration for height constraints
 ///
 /// # Returns
 /// The configured ScrolledWindow widget
-fn create_scrolled_window(list_box: &ListBox, config: &config::Config) -> ScrolledWindow {
+fn create_scrolled_window(list_view: &ListView, config: &config::Config) -> ScrolledWindow {
     ScrolledWindow::builder()
-        .child(list_box)
+        .child(list_view)
         .vscrollbar_policy(gtk4::PolicyType::Automatic)
         .hscrollbar_policy(gtk4::PolicyType::Never)
         .propagate_natural_height(true)
@@ -82,125 +209,304 @@ fn create_scrolled_window(list_box: &ListBox, config: &config::Config) -> Scroll
 /// # Arguments
 /// * `entry` - The text entry widget
 /// * `scrolled_window` - The scrolled window containing the app list
+/// * `preview_pane` - The pane previewing the selected item
+/// * `config` - Application configuration for the position of the preview pane
 ///
 /// # Returns
 /// The configured Box container
-fn create_main_container(entry: &Entry, scrolled_window: &ScrolledWindow) -> Box {
+fn create_main_container(
+    entry: &Entry,
+    scrolled_window: &ScrolledWindow,
+    preview_pane: &ScrolledWindow,
+    config: &config::Config,
+) -> Box {
     let main_box = Box::builder().orientation(Orientation::Vertical).build();
+    let orientation = match config.preview.position {
+        config::PreviewPosition::Right => Orientation::Horizontal,
+        config::PreviewPosition::Bottom => Orientation::Vertical,
+    };
+    let results_box = Box::builder()
+        .orientation(orientation)
+        .homogeneous(orientation == Orientation::Horizontal)
+        .build();
+    scrolled_window.set_hexpand(true);
 
+    results_box.append(scrolled_window);
+    results_box.append(preview_pane);
     main_box.append(entry);
-    main_box.append(scrolled_window);
+    main_box.append(&results_box);
     main_box
 }
 
-/// Populates the list box with filtered items and shows/hides the scrolled window
+/// Populates the list with filtered items
+///
+/// The providers are asked in the background and their results are added to the list
+/// as they arrive. Those still working on the previous query are cancelled.
 ///
 /// # Arguments
-/// * `list_box` - The list box to populate
-/// * `scrolled_window` - The scrolled window to show/hide
+/// * `model` - The items displayed in the list
 /// * `applications` - All available applications
 /// * `config` - Application configuration containing custom commands
+/// * `overlay` - The overlay, provider results aren't listed while it is displayed
+/// * `search` - The search state receiving the results
 /// * `query` - The search query to filter by
 fn populate_app_list(
-    list_box: &ListBox,
-    scrolled_window: &ScrolledWindow,
-    applications: &[application::Application],
-    config: &config::Config,
+    model: &gio::ListStore,
+    applications: &Arc<Vec<application::Application>>,
+    config: &Arc<config::Config>,
+    overlay: &Overlay,
+    search: &Search,
     query: &str,
 ) {
-    while let Some(child) = list_box.first_child() {
-        list_box.remove(&child);
+    let results = search::Results::new(application::filter_applications(
+        applications,
+        config,
+        query,
+    ));
+    show_items(model, results.items());
+    let wants_providers = results.wants_providers();
+
+    let previous = {
+        let mut state = search.borrow_mut();
+        state.results = results;
+        state.cancellable.take()
+    };
+    if let Some(cancellable) = previous {
+        cancellable.cancel();
+    }
+    if !wants_providers {
+        return;
     }
 
-    let filtered_items = application::filter_applications(applications, &config, query);
+    let model_clone = model.clone();
+    let overlay_clone = overlay.clone();
+    let search_clone = search.clone();
 
-    if filtered_items.is_empty() {
-        scrolled_window.set_visible(false);
-    } else {
-        scrolled_window.set_visible(true);
+    let cancellable =
+        search::query_providers(applications, config, query, move |provider, items| {
+            add_provider_results(&model_clone, &overlay_clone, &search_clone, provider, items);
+        });
+    search.borrow_mut().cancellable = Some(cancellable);
+}
 
-        for item in filtered_items.iter() {
-            let item_box = create_item_widget(item);
-            list_box.append(&item_box);
-        }
+/// Inserts the results of a provider into the list, keeping the selected row
+///
+/// # Arguments
+/// * `model` - The items displayed in the list
+/// * `overlay` - The overlay, the list is left alone while it is displayed
+/// * `search` - The search state receiving the results
+/// * `provider` - The provider that answered
+/// * `items` - Its results
+fn add_provider_results(
+    model: &gio::ListStore,
+    overlay: &Overlay,
+    search: &Search,
+    provider: search::Provider,
+    items: Vec<queryable::Queryable>,
+) {
+    let objects = boxed_items(&items);
+    let position = search.borrow_mut().results.insert(provider, items);
+    if !overlay.borrow().displayed {
+        model.splice(position as u32, 0, &objects);
+    }
+}
+
+/// Replaces the items displayed in the list
+///
+/// # Arguments
+/// * `model` - The items displayed in the list
+/// * `items` - The items to display
+fn show_items(model: &gio::ListStore, items: &[queryable::Queryable]) {
+    model.splice(0, model.n_items(), &boxed_items(items));
+}
+
+/// Wraps items so they can be stored in the list model
+///
+/// # Arguments
+/// * `items` - The items to wrap
+///
+/// # Returns
+/// One object per item, in the same order
+fn boxed_items(items: &[queryable::Queryable]) -> Vec<glib::BoxedAnyObject> {
+    items
+        .iter()
+        .cloned()
+        .map(glib::BoxedAnyObject::new)
+        .collect()
+}
 
-        select_first_item(list_box);
+/// Returns the selection of the list view
+///
+/// # Arguments
+/// * `list_view` - The list view widget
+///
+/// # Returns
+/// The single selection wrapping the list model
+fn selection(list_view: &ListView) -> SingleSelection {
+    list_view
+        .model()
+        .and_downcast::<SingleSelection>()
+        .expect("The list view uses a single selection")
+}
+
+/// Returns the item of the selected row
+///
+/// # Arguments
+/// * `list_view` - The list view widget
+///
+/// # Returns
+/// The selected item, or None if the list is empty
+fn selected_item(list_view: &ListView) -> Option<queryable::Queryable> {
+    selection(list_view)
+        .selected_item()
+        .and_downcast::<glib::BoxedAnyObject>()
+        .map(|object| object.borrow::<queryable::Queryable>().clone())
+}
+
+/// Selects a row and scrolls the list so it is visible
+///
+/// # Arguments
+/// * `list_view` - The list view widget
+/// * `position` - The position of the row
+fn select_item(list_view: &ListView, position: u32) {
+    selection(list_view).set_selected(position);
+    if let Err(error) =
+        list_view.activate_action("list.scroll-to-item", Some(&position.to_variant()))
+    {
+        error!("Unable to scroll to row {position}: {error}");
     }
 }
 
-/// Creates a widget for displaying a list item with name and description
+/// Shows the list only when the model has items, and selects the first row whenever
+/// the items are replaced
+///
+/// Rows added without replacing any, like the results of a provider, keep the selection.
 ///
 /// # Arguments
-/// * `item` - The list item to create a widget for
+/// * `list_view` - The list view widget
+/// * `scrolled_window` - The scrolled window containing the list view
+/// * `model` - The items displayed in the list
+fn follow_model_changes(
+    list_view: &ListView,
+    scrolled_window: &ScrolledWindow,
+    model: &gio::ListStore,
+) {
+    let list_view_clone = list_view.clone();
+    let scrolled_window_clone = scrolled_window.clone();
+
+    model.connect_items_changed(move |model, _, removed, _| {
+        scrolled_window_clone.set_visible(model.n_items() > 0);
+        if removed > 0 && model.n_items() > 0 {
+            select_item(&list_view_clone, 0);
+        }
+    });
+}
+
+/// Creates an empty list row, with an icon next to the name and description
 ///
 /// # Returns
-/// A Box widget containing the formatted item
-fn create_item_widget(item: &queryable::Queryable) -> Box {
+/// A Box widget to fill with `fill_item_widget`
+fn create_item_widget() -> Box {
+    let image = Image::builder().pixel_size(24).visible(false).build();
+    let name_label = Label::builder().halign(gtk4::Align::Start).build();
+    let desc_label = Label::builder()
+        .halign(gtk4::Align::Start)
+        .visible(false)
+        .build();
+
     let item_box = Box::builder()
         .orientation(Orientation::Vertical)
         .css_classes(["item-container"])
         .build();
-
-    // Main label (name) with appropriate styling based on item type
-    let (name_css_class, desc_css_class) = item.classes();
-
-    let name_label = Label::builder()
-        .label(&item.display_name())
-        .halign(gtk4::Align::Start)
-        .css_classes([name_css_class])
-        .build();
     item_box.append(&name_label);
+    item_box.append(&desc_label);
 
-    if let Some(description) = item.description() {
-        let desc_label = Label::builder()
-            .label(&description)
-            .halign(gtk4::Align::Start)
-            .css_classes([desc_css_class])
-            .build();
-
-        item_box.append(&desc_label);
-    }
-
-    item_box
+    let row_box = Box::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(8)
+        .build();
+    row_box.append(&image);
+    row_box.append(&item_box);
+    row_box
 }
 
-/// Selects the first item in the list box and scrolls to it
+/// Displays an item in a row made by `create_item_widget`
 ///
 /// # Arguments
-/// * `list_box` - The list box to select the first item in
-fn select_first_item(list_box: &ListBox) {
-    if let Some(first_child) = list_box.first_child() {
-        select_and_scroll_to(list_box, first_child)
+/// * `row_box` - The row to fill
+/// * `item` - The list item to display
+fn fill_item_widget(row_box: &Box, item: &queryable::Queryable) {
+    let Some(image) = row_box.first_child().and_downcast::<Image>() else {
+        return;
+    };
+    let Some(item_box) = image.next_sibling() else {
+        return;
+    };
+    let Some(name_label) = item_box.first_child().and_downcast::<Label>() else {
+        return;
+    };
+    let Some(desc_label) = name_label.next_sibling().and_downcast::<Label>() else {
+        return;
+    };
+
+    match item.icon() {
+        Some(icon) if icon.starts_with('/') => image.set_from_file(Some(icon)),
+        Some(icon) => image.set_icon_name(Some(icon)),
+        None => image.clear(),
     }
+    image.set_visible(item.icon().is_some());
+
+    // Main label (name) with appropriate styling based on item type
+    let (name_css_class, desc_css_class) = item.classes();
+    name_label.set_label(&item.display_name());
+    name_label.set_css_classes(&[name_css_class]);
+
+    let description = item.description();
+    desc_label.set_label(description.as_deref().unwrap_or_default());
+    desc_label.set_css_classes(&[desc_css_class]);
+    desc_label.set_visible(description.is_some());
 }
 
 /// Sets up text filtering for the entry widget
 ///
 /// # Arguments
 /// * `entry` - The text entry widget
-/// * `list_box` - The list box to update
-/// * `scrolled_window` - The scrolled window to show/hide
+/// * `model` - The items displayed in the list
 /// * `applications` - All available applications
 /// * `config` - Application configuration containing custom commands
+/// * `overlay` - The overlay to dismiss when the query changes, or the prompt to answer
+/// * `search` - The search state receiving the results
 fn setup_text_filtering(
     entry: &Entry,
-    list_box: &ListBox,
-    scrolled_window: &ScrolledWindow,
-    applications: &[application::Application],
-    config: &config::Config,
+    model: &gio::ListStore,
+    applications: &Arc<Vec<application::Application>>,
+    config: &Arc<config::Config>,
+    overlay: &Overlay,
+    search: &Search,
 ) {
-    let applications_clone = applications.to_vec();
-    let list_box_clone = list_box.clone();
-    let scrolled_window_clone = scrolled_window.clone();
+    let applications_clone = applications.clone();
+    let model_clone = model.clone();
     let config_clone = config.clone();
+    let overlay_clone = overlay.clone();
+    let search_clone = search.clone();
 
     entry.connect_changed(move |entry| {
         let text = entry.text();
+        if let Some(task) = overlay_clone.borrow_mut().task.take() {
+            task.cancel();
+        }
+        if overlay_clone.borrow().prompt.is_some() {
+            show_prompt(&model_clone, &overlay_clone, &text);
+            return;
+        }
+
+        overlay_clone.borrow_mut().displayed = false;
         populate_app_list(
-            &list_box_clone,
-            &scrolled_window_clone,
+            &model_clone,
             &applications_clone,
             &config_clone,
+            &overlay_clone,
+            &search_clone,
             &text,
         );
     });
@@ -231,234 +537,553 @@ fn create_window(app: &Application, main_box: &Box, config: &config::Config) ->
     window
 }
 
-/// Handles item launch when a list item is clicked
+/// Sets up click handling for list items, right-click opens the secondary actions
 ///
 /// # Arguments
-/// * `row` - The clicked row
+/// * `list_view` - The list view widget
+/// * `model` - The items displayed in the list
 /// * `entry` - The text entry widget
-/// * `applications` - All available applications
 /// * `config` - Application configuration
-/// * `window` - The main window to close after launch
-fn handle_item_click(
-    row: &ListBoxRow,
+/// * `overlay` - The overlay showing secondary actions
+/// * `search` - The search state holding the results
+/// * `window` - The main window
+fn setup_click_handling(
+    list_view: &ListView,
+    model: &gio::ListStore,
     entry: &Entry,
-    applications: &[application::Application],
-    config: &config::Config,
+    config: &Arc<config::Config>,
+    overlay: &Overlay,
+    search: &Search,
     window: &ApplicationWindow,
 ) {
-    let index = row.index() as usize;
-    let text = entry.text();
-    let filtered_items = application::filter_applications(applications, &config, &text);
+    // Rows select themselves when pressed, so the click acts on the selected item
+    let click = GestureClick::builder().button(0).build();
+    let list_view_clone = list_view.clone();
+    let entry_clone = entry.clone();
+    let model_clone = model.clone();
+    let config_clone = config.clone();
+    let overlay_clone = overlay.clone();
+    let search_clone = search.clone();
+    let window_clone = window.clone();
 
-    if let Some(item) = filtered_items.get(index) {
-        item.action(config);
-        window.close();
+    click.connect_released(move |gesture, _, x, y| {
+        let on_row = list_view_clone
+            .pick(x, y, gtk4::PickFlags::DEFAULT)
+            .is_some_and(|widget| &widget != list_view_clone.upcast_ref::<gtk4::Widget>());
+        if !on_row {
+            return;
+        }
+
+        if gesture.current_button() == gtk4::gdk::BUTTON_SECONDARY {
+            open_secondary_actions(&list_view_clone, &model_clone, &overlay_clone);
+        } else {
+            activate_selected_item(
+                &entry_clone,
+                &list_view_clone,
+                &model_clone,
+                &config_clone,
+                &overlay_clone,
+                &search_clone,
+                &window_clone,
+            );
+        }
+    });
+
+    list_view.add_controller(click);
+}
+
+/// Replaces the list with the secondary actions of the selected item
+///
+/// # Arguments
+/// * `list_view` - The list view widget
+/// * `model` - The items displayed in the list
+/// * `overlay` - The overlay receiving the secondary actions
+fn open_secondary_actions(list_view: &ListView, model: &gio::ListStore, overlay: &Overlay) {
+    if let Some(item) = selected_item(list_view) {
+        let actions = item.secondary_actions();
+        if !actions.is_empty() {
+            show_items(model, &actions);
+            overlay.borrow_mut().displayed = true;
+        }
     }
 }
 
-/// Sets up click handling for list items
+/// Replaces the entry text with the completion of the selected item
 ///
 /// # Arguments
-/// * `list_box` - The list box widget
 /// * `entry` - The text entry widget
-/// * `applications` - All available applications
+/// * `list_view` - The list view widget
+///
+/// # Returns
+/// Whether the entry text was completed
+fn complete_selected_item(entry: &Entry, list_view: &ListView) -> bool {
+    match selected_item(list_view).and_then(|item| item.completion()) {
+        Some(completion) if completion != entry.text() => {
+            entry.set_text(&completion);
+            entry.set_position(-1);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Dismisses the overlay or cancels the prompt, and shows the search results again
+///
+/// # Arguments
+/// * `model` - The items displayed in the list
+/// * `entry` - The text entry widget
 /// * `config` - Application configuration
-/// * `window` - The main window
-fn setup_click_handling(
-    list_box: &ListBox,
+/// * `overlay` - The overlay to dismiss
+/// * `search` - The search state holding the results
+///
+/// # Returns
+/// Whether an overlay was displayed
+fn close_overlay(
+    model: &gio::ListStore,
     entry: &Entry,
-    applications: &[application::Application],
     config: &config::Config,
-    window: &ApplicationWindow,
-) {
-    let applications_clone = applications.to_vec();
-    let entry_clone = entry.clone();
-    let config_clone = config.clone();
-    let window_clone = window.clone();
+    overlay: &Overlay,
+    search: &Search,
+) -> bool {
+    let OverlayState {
+        displayed,
+        prompt,
+        task,
+    } = overlay.take();
+    if let Some(task) = task {
+        task.cancel();
+    }
+    if !displayed && prompt.is_none() {
+        return false;
+    }
 
-    list_box.connect_row_activated(move |_, row| {
-        handle_item_click(
-            row,
-            &entry_clone,
-            &applications_clone,
-            &config_clone,
-            &window_clone,
-        );
-    });
+    if prompt.is_some() {
+        entry.set_placeholder_text(Some(&config.placeholder));
+        entry.set_text("");
+    }
+
+    show_items(model, search.borrow().results.items());
+    true
 }
 
-/// Scrolls to ensure the selected row is visible
+/// Moves the selection for a navigation action
 ///
 /// # Arguments
-/// * `list_box` - The list box containing the row
-/// * `row` - The row to scroll to
-fn scroll_to_row(list_box: &ListBox, row: &ListBoxRow) {
-    let mut current = Some(list_box.clone().upcast::<gtk4::Widget>());
-    let mut scrolled_window: Option<ScrolledWindow> = None;
+/// * `action` - The triggered action
+/// * `list_view` - The list view to navigate
+///
+/// # Returns
+/// Whether the action moves the selection
+fn navigate(action: config::KeyAction, list_view: &ListView) -> bool {
+    let selection = selection(list_view);
+    let n_items = selection.n_items();
+    let selected = match selection.selected() {
+        gtk4::INVALID_LIST_POSITION => None,
+        selected => Some(selected),
+    };
 
-    while let Some(widget) = current {
-        if let Ok(sw) = widget.clone().downcast::<ScrolledWindow>() {
-            scrolled_window = Some(sw);
-            break;
-        }
-        current = widget.parent();
-    }
+    let target = match action {
+        config::KeyAction::Next => selected.map_or(0, |selected| selected + 1),
+        config::KeyAction::Previous => selected.unwrap_or(0).saturating_sub(1),
+        config::KeyAction::PageDown => selected.unwrap_or(0) + rows_per_page(list_view),
+        config::KeyAction::PageUp => selected
+            .unwrap_or(0)
+            .saturating_sub(rows_per_page(list_view)),
+        config::KeyAction::First => 0,
+        config::KeyAction::Last => n_items.saturating_sub(1),
+        _ => return false,
+    };
 
-    if let Some(scrolled_window) = scrolled_window {
-        glib::idle_add_local_once({
-            let row_clone = row.clone();
-            let scrolled_window_clone = scrolled_window.clone();
-            move || {
-                let adjustment = scrolled_window_clone.vadjustment();
-
-                let row_index = row_clone.index() as f64;
-
-                // Estimate row height (approximate) - accounts for description line too
-                let estimated_row_height = 80.0; // Approximate row height with description
-                let row_position = row_index * estimated_row_height;
-
-                let visible_top = adjustment.value();
-                let visible_bottom = visible_top + adjustment.page_size();
-                let row_bottom = row_position + estimated_row_height;
-
-                if row_position < visible_top {
-                    adjustment.set_value(row_position.max(0.0));
-                } else if row_bottom > visible_bottom {
-                    let new_value = (row_bottom - adjustment.page_size()).max(0.0);
-                    let max_value = adjustment.upper() - adjustment.page_size();
-                    adjustment.set_value(new_value.min(max_value.max(0.0)));
-                }
-            }
-        });
+    if n_items > 0 {
+        select_item(list_view, target.min(n_items - 1));
     }
+    true
 }
 
-/// Handles keyboard navigation (up/down arrows)
+/// Returns how many rows fit in the visible part of the list
 ///
 /// # Arguments
-/// * `key` - The pressed key
-/// * `list_box` - The list box to navigate
+/// * `list_view` - The list view widget
 ///
 /// # Returns
-/// Whether the key was handled
-fn handle_navigation_key(key: gtk4::gdk::Key, list_box: &ListBox) -> bool {
-    match key {
-        gtk4::gdk::Key::Down => {
-            if let Some(selected) = list_box.selected_row() {
-                if let Some(next) = selected.next_sibling() {
-                    select_and_scroll_to(list_box, next);
-                }
-            } else if let Some(first) = list_box.first_child() {
-                select_and_scroll_to(list_box, first);
-            }
-            true
-        }
-        gtk4::gdk::Key::Up => {
-            if let Some(selected) = list_box.selected_row() {
-                if let Some(prev) = selected.prev_sibling() {
-                    select_and_scroll_to(list_box, prev)
-                }
-            }
-            true
-        }
-        _ => false,
+/// The number of fully visible rows, at least 1
+fn rows_per_page(list_view: &ListView) -> u32 {
+    let n_items = selection(list_view).n_items();
+    let Some(adjustment) = list_view.vadjustment() else {
+        return 1;
+    };
+    if n_items == 0 || adjustment.upper() <= 0.0 {
+        return 1;
     }
+
+    let row_height = adjustment.upper() / f64::from(n_items);
+    ((adjustment.page_size() / row_height) as u32).max(1)
 }
 
-fn select_and_scroll_to(list_box: &ListBox, selected: gtk4::Widget) {
-    if let Some(inner_item) = selected.downcast_ref::<ListBoxRow>() {
-        list_box.select_row(Some(inner_item));
-        scroll_to_row(list_box, inner_item);
+/// Handles item launch via Enter key or click
+///
+/// The window stays open with an error row when the launch fails. Nothing happens while
+/// a previous activation still runs.
+///
+/// # Arguments
+/// * `entry` - The text entry widget
+/// * `list_view` - The list view widget
+/// * `model` - The items displayed in the list
+/// * `config` - Application configuration
+/// * `overlay` - The overlay, displayed instead of the search results when set
+/// * `search` - The search state holding the results
+/// * `window` - The main window to close after launch
+fn activate_selected_item(
+    entry: &Entry,
+    list_view: &ListView,
+    model: &gio::ListStore,
+    config: &Arc<config::Config>,
+    overlay: &Overlay,
+    search: &Search,
+    window: &ApplicationWindow,
+) {
+    if overlay.borrow().task.is_some() {
+        return;
     }
+    let Some(item) = selected_item(list_view) else {
+        window.close();
+        return;
+    };
+
+    let outcome = item
+        .action(config)
+        .map_err(|message| launch_error(&item.display_name(), message));
+    apply_outcome(outcome, entry, model, config, overlay, search, window);
 }
 
-/// Handles item launch via Enter key
+/// Does what the activation of an item led to
 ///
 /// # Arguments
+/// * `outcome` - The outcome, or the error row to show
 /// * `entry` - The text entry widget
-/// * `list_box` - The list box widget
-/// * `applications` - All available applications
+/// * `model` - The items displayed in the list
 /// * `config` - Application configuration
+/// * `overlay` - The overlay, displayed instead of the search results when set
+/// * `search` - The search state holding the results
 /// * `window` - The main window to close after launch
-fn handle_enter_key(
+fn apply_outcome(
+    outcome: Result<queryable::Outcome, queryable::Queryable>,
     entry: &Entry,
-    list_box: &ListBox,
-    applications: &[application::Application],
-    config: &config::Config,
+    model: &gio::ListStore,
+    config: &Arc<config::Config>,
+    overlay: &Overlay,
+    search: &Search,
     window: &ApplicationWindow,
 ) {
-    let text = entry.text();
-    let filtered_items = application::filter_applications(applications, &config, &text);
+    match outcome {
+        Ok(queryable::Outcome::Close) => window.close(),
+        Ok(queryable::Outcome::Prompt(prompt)) => start_prompt(entry, model, overlay, prompt),
+        Ok(queryable::Outcome::Show(items)) => {
+            if overlay.borrow_mut().prompt.take().is_some() {
+                entry.set_placeholder_text(Some(&config.placeholder));
+            }
+            show_items(model, &items);
+            overlay.borrow_mut().displayed = true;
+        }
+        Ok(queryable::Outcome::Dismiss) => {
+            close_overlay(model, entry, config, overlay, search);
+        }
+        Ok(queryable::Outcome::Stay) => {}
+        Ok(queryable::Outcome::Pending(task)) => {
+            run_task(task, entry, model, config, overlay, search, window);
+        }
+        Err(error) => {
+            show_items(model, &[error]);
+            overlay.borrow_mut().displayed = true;
+        }
+    }
+}
 
-    let index = if let Some(selected_row) = list_box.selected_row() {
-        let index = selected_row.index() as usize;
-        index
-    } else {
-        0
-    };
+/// Runs the slow part of an activation off the main thread, showing a pending row
+/// until its outcome arrives
+///
+/// The outcome is dropped if the task gets cancelled in the meantime, by Escape or
+/// by typing.
+///
+/// # Arguments
+/// * `task` - The task to run
+/// * `entry` - The text entry widget
+/// * `model` - The items displayed in the list
+/// * `config` - Application configuration
+/// * `overlay` - The overlay showing the pending row
+/// * `search` - The search state holding the results
+/// * `window` - The main window
+fn run_task(
+    task: queryable::Task,
+    entry: &Entry,
+    model: &gio::ListStore,
+    config: &Arc<config::Config>,
+    overlay: &Overlay,
+    search: &Search,
+    window: &ApplicationWindow,
+) {
+    let name = task.name.clone();
+    let cancellable = gio::Cancellable::new();
+    show_items(
+        model,
+        &[queryable::Queryable::Pending { name: name.clone() }],
+    );
+    {
+        let mut state = overlay.borrow_mut();
+        state.displayed = true;
+        state.task = Some(cancellable.clone());
+    }
 
-    if let Some(item) = filtered_items.get(index) {
-        item.action(config);
-    } else if !filtered_items.is_empty() {
-        if let Some(first_item) = filtered_items.first() {
-            first_item.action(config);
+    let entry = entry.clone();
+    let model = model.clone();
+    let config = config.clone();
+    let overlay = overlay.clone();
+    let search = search.clone();
+    let window = window.clone();
+
+    glib::spawn_future_local(async move {
+        let worker_cancellable = cancellable.clone();
+        let outcome = gio::spawn_blocking(move || task.run(&worker_cancellable))
+            .await
+            .unwrap_or_else(|_| Err("The task panicked".to_string()));
+        if cancellable.is_cancelled() {
+            return;
         }
+
+        overlay.borrow_mut().task = None;
+        let outcome = outcome.map_err(|message| launch_error(&name, message));
+        apply_outcome(outcome, &entry, &model, &config, &overlay, &search, &window);
+    });
+}
+
+fn launch_error(name: &str, message: String) -> queryable::Queryable {
+    queryable::Queryable::Error {
+        title: format!("Unable to launch {name}"),
+        message,
     }
-    window.close();
 }
 
-/// Sets up Enter key handling using Entry's activate signal and separate navigation controller
+/// Turns the entry into the input of a custom command parameter
+///
+/// # Arguments
+/// * `entry` - The text entry widget
+/// * `model` - The items displayed in the list
+/// * `overlay` - The overlay receiving the prompt
+/// * `prompt` - The prompt for the next parameter
+fn start_prompt(
+    entry: &Entry,
+    model: &gio::ListStore,
+    overlay: &Overlay,
+    prompt: queryable::Prompt,
+) {
+    entry.set_placeholder_text(Some(&prompt.label()));
+    overlay.borrow_mut().prompt = Some(prompt);
+
+    entry.set_text("");
+    show_prompt(model, overlay, "");
+}
+
+/// Shows the candidate values of the prompted parameter for the entry text
+///
+/// # Arguments
+/// * `model` - The items displayed in the list
+/// * `overlay` - The overlay holding the prompt
+/// * `text` - The entry text
+fn show_prompt(model: &gio::ListStore, overlay: &Overlay, text: &str) {
+    let Some(items) = overlay
+        .borrow()
+        .prompt
+        .as_ref()
+        .map(|prompt| prompt.items(text))
+    else {
+        return;
+    };
+
+    show_items(model, &items);
+    overlay.borrow_mut().displayed = true;
+}
+
+/// Sets up the key bindings of the launcher actions
+///
+/// The keys come from the `[keybindings]` configuration. Ctrl+1 to Ctrl+9 activate the
+/// matching result directly, unless they are bound to an action. Escape closes the
+/// secondary actions before the window.
 ///
 /// # Arguments
 /// * `window` - The main window
 /// * `entry` - The text entry widget
-/// * `list_box` - The list box widget
-/// * `applications` - All available applications
+/// * `list_view` - The list view widget
+/// * `model` - The items displayed in the list
 /// * `config` - Application configuration
+/// * `overlay` - The overlay showing secondary actions
+/// * `search` - The search state holding the results
 fn setup_keyboard_handling(
     window: &ApplicationWindow,
     entry: &Entry,
-    list_box: &ListBox,
-    applications: &[application::Application],
-    config: &config::Config,
+    list_view: &ListView,
+    model: &gio::ListStore,
+    config: &Arc<config::Config>,
+    overlay: &Overlay,
+    search: &Search,
 ) {
+    let keymap = keybindings::Keymap::new(&config.keybindings);
+    let key_controller = EventControllerKey::new();
+    key_controller.set_propagation_phase(gtk4::PropagationPhase::Capture);
     let window_clone = window.clone();
-    let list_box_clone = list_box.clone();
-    let applications_clone = applications.to_vec();
-    let config_clone = config.clone();
+    let list_view_clone = list_view.clone();
+    let model_clone = model.clone();
     let entry_clone = entry.clone();
+    let config_clone = config.clone();
+    let overlay_clone = overlay.clone();
+    let search_clone = search.clone();
 
-    entry.connect_activate(move |_| {
-        handle_enter_key(
-            &entry_clone,
-            &list_box_clone,
-            &applications_clone,
-            &config_clone,
-            &window_clone,
-        );
-    });
+    key_controller.connect_key_pressed(move |_, key, _, modifiers| {
+        let Some(action) = keymap.lookup(key, modifiers) else {
+            let Some(position) = keybindings::picked_position(key, modifiers) else {
+                return gtk4::glib::Propagation::Proceed;
+            };
 
-    let key_controller = EventControllerKey::new();
-    let window_clone2 = window.clone();
-    let list_box_clone2 = list_box.clone();
+            if position < model_clone.n_items() {
+                select_item(&list_view_clone, position);
+                activate_selected_item(
+                    &entry_clone,
+                    &list_view_clone,
+                    &model_clone,
+                    &config_clone,
+                    &overlay_clone,
+                    &search_clone,
+                    &window_clone,
+                );
+            }
+            return gtk4::glib::Propagation::Stop;
+        };
 
-    key_controller.connect_key_pressed(move |_, key, _, _| match key {
-        gtk4::gdk::Key::Escape => {
-            window_clone2.close();
-            gtk4::glib::Propagation::Stop
-        }
-        _ => {
-            if handle_navigation_key(key, &list_box_clone2) {
-                gtk4::glib::Propagation::Stop
-            } else {
-                gtk4::glib::Propagation::Proceed
+        match action {
+            config::KeyAction::Activate => activate_selected_item(
+                &entry_clone,
+                &list_view_clone,
+                &model_clone,
+                &config_clone,
+                &overlay_clone,
+                &search_clone,
+                &window_clone,
+            ),
+            config::KeyAction::AlternateAction => {
+                open_secondary_actions(&list_view_clone, &model_clone, &overlay_clone);
+            }
+            config::KeyAction::Complete => {
+                if !complete_selected_item(&entry_clone, &list_view_clone) {
+                    open_secondary_actions(&list_view_clone, &model_clone, &overlay_clone);
+                }
+            }
+            config::KeyAction::Clear => entry_clone.set_text(""),
+            config::KeyAction::Close => {
+                if !close_overlay(
+                    &model_clone,
+                    &entry_clone,
+                    &config_clone,
+                    &overlay_clone,
+                    &search_clone,
+                ) {
+                    window_clone.close();
+                }
+            }
+            config::KeyAction::TogglePreview => {
+                if let Err(error) =
+                    WidgetExt::activate_action(&window_clone, "win.toggle-preview", None)
+                {
+                    error!("Unable to toggle the preview pane: {error}");
+                }
+            }
+            _ => {
+                navigate(action, &list_view_clone);
             }
         }
+        gtk4::glib::Propagation::Stop
     });
 
     window.add_controller(key_controller);
 }
 
+/// Keeps the preview pane in sync with the selected item, and adds the action toggling it
+///
+/// # Arguments
+/// * `window` - The main window
+/// * `list_view` - The list view widget
+/// * `preview_pane` - The pane previewing the selected item
+/// * `config` - Application configuration
+fn setup_preview(
+    window: &ApplicationWindow,
+    list_view: &ListView,
+    preview_pane: &ScrolledWindow,
+    config: &Arc<config::Config>,
+) {
+    let toggle = gio::SimpleAction::new_stateful(
+        "toggle-preview",
+        None,
+        &config.preview.enabled.to_variant(),
+    );
+    let list_view_clone = list_view.clone();
+    let preview_pane_clone = preview_pane.clone();
+    let config_clone = config.clone();
+
+    toggle.connect_activate(move |toggle, _| {
+        let enabled = !toggle
+            .state()
+            .and_then(|state| state.get::<bool>())
+            .unwrap_or_default();
+        toggle.set_state(&enabled.to_variant());
+        update_preview(
+            &list_view_clone,
+            &preview_pane_clone,
+            &config_clone,
+            enabled,
+        );
+    });
+
+    let list_view_clone = list_view.clone();
+    let preview_pane_clone = preview_pane.clone();
+    let config_clone = config.clone();
+    let toggle_clone = toggle.clone();
+
+    selection(list_view).connect_selected_item_notify(move |_| {
+        let enabled = toggle_clone
+            .state()
+            .and_then(|state| state.get::<bool>())
+            .unwrap_or_default();
+        update_preview(
+            &list_view_clone,
+            &preview_pane_clone,
+            &config_clone,
+            enabled,
+        );
+    });
+
+    window.add_action(&toggle);
+}
+
+/// Shows the preview of the selected item, or hides the pane
+///
+/// # Arguments
+/// * `list_view` - The list view widget
+/// * `preview_pane` - The pane previewing the selected item
+/// * `config` - Application configuration
+/// * `enabled` - Whether the preview pane is toggled on
+fn update_preview(
+    list_view: &ListView,
+    preview_pane: &ScrolledWindow,
+    config: &config::Config,
+    enabled: bool,
+) {
+    match selected_item(list_view).filter(|_| enabled) {
+        Some(item) => {
+            preview::show(preview_pane, &preview::build(&item, config));
+            preview_pane.set_visible(true);
+        }
+        None => preview_pane.set_visible(false),
+    }
+}
+
 /// Shows the window and sets initial focus
 ///
 /// # Arguments
@@ -479,18 +1104,29 @@ fn show_window(window: &ApplicationWindow, entry: &Entry, scrolled_window: &Scro
 /// # Arguments
 /// * `app` - The GTK Application instance
 fn build_ui(app: &Application) {
-    let config = config::load_config();
-    let applications = application::scan_applications(&config);
+    let config = Arc::new(config::load_config());
+    let applications = Arc::new(application::scan_applications(&config));
 
     let entry = create_entry(&config);
-    let list_box = create_list_box();
-    let scrolled_window = create_scrolled_window(&list_box, &config);
-    let main_box = create_main_container(&entry, &scrolled_window);
+    let model = gio::ListStore::new::<glib::BoxedAnyObject>();
+    let list_view = create_list_view(&model);
+    let scrolled_window = create_scrolled_window(&list_view, &config);
+    let preview_pane = preview::create_pane(&config);
+    let main_box = create_main_container(&entry, &scrolled_window, &preview_pane, &config);
     let window = create_window(app, &main_box, &config);
+    let overlay: Overlay = Rc::new(RefCell::new(OverlayState::default()));
+    let search: Search = Rc::new(RefCell::new(SearchState::default()));
+    follow_model_changes(&list_view, &scrolled_window, &model);
+
+    setup_text_filtering(&entry, &model, &applications, &config, &overlay, &search);
+    setup_click_handling(
+        &list_view, &model, &entry, &config, &overlay, &search, &window,
+    );
+    setup_keyboard_handling(
+        &window, &entry, &list_view, &model, &config, &overlay, &search,
+    );
 
-    setup_text_filtering(&entry, &list_box, &scrolled_window, &applications, &config);
-    setup_click_handling(&list_box, &entry, &applications, &config, &window);
-    setup_keyboard_handling(&window, &entry, &list_box, &applications, &config);
+    setup_preview(&window, &list_view, &preview_pane, &config);
 
     show_window(&window, &entry, &scrolled_window);
 }
@@ -0,0 +1,358 @@
+use gtk4::gio;
+use gtk4::prelude::*;
+use log::{debug, error};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::{Plugin, Plugins};
+use crate::queryable::{Outcome, Queryable, Task};
+use crate::script;
+use crate::template;
+
+/// Version of the plugin protocol, sent in the `initialize` request
+const PROTOCOL_VERSION: u64 = 1;
+
+/// How long a plugin may take to answer each request
+const INITIALIZE_TIMEOUT: Duration = Duration::from_secs(2);
+const QUERY_TIMEOUT: Duration = Duration::from_millis(250);
+const ACTIVATE_TIMEOUT: Duration = Duration::from_secs(10);
+/// How often a pending request checks whether its query is outdated
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// How many times a plugin is restarted after crashing before it is left stopped
+const MAX_RESTARTS: u32 = 3;
+
+/// Plugin processes, started on first use and kept for the launcher session
+///
+/// Each plugin has its own lock, held while a request waits for its answer, so a slow
+/// plugin doesn't hold up requests to the others.
+static PROCESSES: LazyLock<Mutex<HashMap<String, Arc<Mutex<Slot>>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// A result row returned by a plugin
+#[derive(Deserialize, Clone, Debug)]
+pub struct PluginItem {
+    /// Passed back to the plugin on activation, the title if missing
+    pub id: Option<String>,
+    pub title: String,
+    pub description: Option<String>,
+    pub icon: Option<String>,
+    /// Rank among the results of every plugin, higher first
+    #[serde(default)]
+    pub score: f64,
+}
+
+#[derive(Deserialize, Default)]
+struct Results {
+    #[serde(default)]
+    items: Vec<PluginItem>,
+}
+
+#[derive(Default)]
+struct Slot {
+    process: Option<Process>,
+    crashes: u32,
+}
+
+struct Process {
+    child: Child,
+    stdin: ChildStdin,
+    messages: Receiver<Value>,
+    next_id: u64,
+}
+
+/// Why a request got no result
+enum Failure {
+    /// The plugin process is gone or can't be talked to anymore
+    Crashed(String),
+    /// The plugin answered with an error, or not in time
+    Failed(String),
+}
+
+/// Asks the plugins matching the query for result rows
+///
+/// Plugins with a prefix only answer queries starting with it, the others answer every
+/// query. A plugin not answering in time, or before the query is outdated, is sent a
+/// `$/cancel` notification and its results are left out.
+///
+/// # Arguments
+/// * `plugins` - The configured plugins
+/// * `query` - The search query
+/// * `cancellable` - Cancels the pending request when the query is outdated
+///
+/// # Returns
+/// The rows returned by every plugin, ordered by score
+pub fn query(plugins: &Plugins, query: &str, cancellable: &gio::Cancellable) -> Vec<Queryable> {
+    let mut rows: Vec<(Plugin, PluginItem)> = Vec::new();
+
+    for plugin in plugins.values() {
+        let query = match &plugin.prefix {
+            Some(prefix) => match script::strip_prefix(query, prefix) {
+                Some(rest) => rest,
+                None => continue,
+            },
+            None => query,
+        };
+
+        let params = json!({ "query": query });
+        match call(plugin, "query", params, QUERY_TIMEOUT, Some(cancellable)) {
+            Ok(results) => rows.extend(
+                parse_results(plugin, results)
+                    .into_iter()
+                    .map(|item| (plugin.clone(), item)),
+            ),
+            Err(_) if cancellable.is_cancelled() => break,
+            Err(message) => error!("Plugin {} failed: {message}", plugin.name),
+        }
+    }
+
+    rows.sort_by(|(_, a), (_, b)| b.score.total_cmp(&a.score));
+    rows.into_iter()
+        .map(|(plugin, item)| Queryable::Plugin { plugin, item })
+        .collect()
+}
+
+/// Sends the activated row back to its plugin, off the main thread
+///
+/// # Arguments
+/// * `plugin` - The plugin the row comes from
+/// * `item` - The activated row
+///
+/// # Returns
+/// The task waiting for the plugin, which shows the rows it returned in answer as a
+/// new list, or closes the launcher if there are none
+pub fn activate(plugin: &Plugin, item: &PluginItem) -> Result<Outcome, String> {
+    let id = item.id.clone().unwrap_or_else(|| item.title.clone());
+    let plugin = plugin.clone();
+
+    Ok(Outcome::Pending(Task::new(
+        &item.title,
+        move |cancellable| {
+            let results = call(
+                &plugin,
+                "activate",
+                json!({ "id": id }),
+                ACTIVATE_TIMEOUT,
+                Some(cancellable),
+            )?;
+
+            let items: Vec<Queryable> = parse_results(&plugin, results)
+                .into_iter()
+                .map(|item| Queryable::Plugin {
+                    plugin: plugin.clone(),
+                    item,
+                })
+                .collect();
+
+            if items.is_empty() {
+                Ok(Outcome::Close)
+            } else {
+                Ok(Outcome::Show(items))
+            }
+        },
+    )))
+}
+
+/// Sends a request to a plugin, starting it first if needed
+///
+/// Requests to the same plugin wait for each other. A plugin that crashed is restarted on
+/// the next request, up to `MAX_RESTARTS` times.
+fn call(
+    plugin: &Plugin,
+    method: &str,
+    params: Value,
+    timeout: Duration,
+    cancellable: Option<&gio::Cancellable>,
+) -> Result<Value, String> {
+    let shared_slot = PROCESSES
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .entry(plugin.name.clone())
+        .or_default()
+        .clone();
+    let mut guard = shared_slot
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let slot = &mut *guard;
+
+    let process = match &mut slot.process {
+        Some(process) => process,
+        None if slot.crashes > MAX_RESTARTS => {
+            return Err(format!("Stopped after crashing {} times", slot.crashes));
+        }
+        None => match Process::start(plugin) {
+            Ok(process) => slot.process.insert(process),
+            Err(message) => {
+                slot.crashes += 1;
+                return Err(message);
+            }
+        },
+    };
+
+    match process.request(method, params, timeout, cancellable) {
+        Ok(result) => Ok(result),
+        Err(Failure::Failed(message)) => Err(message),
+        Err(Failure::Crashed(message)) => {
+            slot.process = None;
+            slot.crashes += 1;
+            Err(message)
+        }
+    }
+}
+
+fn parse_results(plugin: &Plugin, results: Value) -> Vec<PluginItem> {
+    if results.is_null() {
+        return Vec::new();
+    }
+
+    match serde_json::from_value::<Results>(results) {
+        Ok(results) => results.items,
+        Err(error) => {
+            error!("Plugin {} returned invalid results: {error}", plugin.name);
+            Vec::new()
+        }
+    }
+}
+
+impl Process {
+    /// Spawns the plugin and checks it speaks the same protocol version
+    fn start(plugin: &Plugin) -> Result<Process, String> {
+        let mut argv = template::split(&plugin.command)?;
+        if argv.is_empty() {
+            return Err("Empty plugin command".to_string());
+        }
+        let program = template::expand_home(&argv.remove(0));
+
+        let mut child = Command::new(&program)
+            .args(&argv)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|error| format!("Unable to start {program}: {error}"))?;
+
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(format!("Unable to talk to {program}"));
+        };
+
+        let (sender, messages) = mpsc::channel();
+        let name = plugin.name.clone();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                match serde_json::from_str::<Value>(&line) {
+                    Ok(message) => {
+                        if sender.send(message).is_err() {
+                            break;
+                        }
+                    }
+                    Err(error) => debug!("Plugin {name} printed {line:?}: {error}"),
+                }
+            }
+        });
+
+        let mut process = Process {
+            child,
+            stdin,
+            messages,
+            next_id: 0,
+        };
+
+        let result = process
+            .request(
+                "initialize",
+                json!({ "protocol_version": PROTOCOL_VERSION }),
+                INITIALIZE_TIMEOUT,
+                None,
+            )
+            .map_err(|(Failure::Crashed(message) | Failure::Failed(message))| message)?;
+
+        match result.get("protocol_version").and_then(Value::as_u64) {
+            Some(PROTOCOL_VERSION) => Ok(process),
+            version => Err(format!(
+                "{program} speaks protocol version {}, expected {PROTOCOL_VERSION}",
+                version.map_or("unknown".to_string(), |version| version.to_string())
+            )),
+        }
+    }
+
+    /// Sends a request and waits for its response, dropping answers to earlier requests
+    ///
+    /// The request is cancelled with a `$/cancel` notification when it times out or
+    /// `cancellable` is cancelled.
+    fn request(
+        &mut self,
+        method: &str,
+        params: Value,
+        timeout: Duration,
+        cancellable: Option<&gio::Cancellable>,
+    ) -> Result<Value, Failure> {
+        self.next_id += 1;
+        let id = self.next_id;
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))?;
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match self
+                .messages
+                .recv_timeout(remaining.min(CANCEL_POLL_INTERVAL))
+            {
+                Ok(message) if message.get("id").and_then(Value::as_u64) == Some(id) => {
+                    return match message.get("error") {
+                        Some(error) => Err(Failure::Failed(
+                            error
+                                .get("message")
+                                .and_then(Value::as_str)
+                                .unwrap_or("Unknown error")
+                                .to_string(),
+                        )),
+                        None => Ok(message.get("result").cloned().unwrap_or(Value::Null)),
+                    };
+                }
+                Ok(message) => debug!("Dropping stale plugin message {message}"),
+                Err(RecvTimeoutError::Timeout) => {
+                    let cancelled =
+                        cancellable.is_some_and(|cancellable| cancellable.is_cancelled());
+                    if !cancelled && !remaining.is_zero() {
+                        continue;
+                    }
+
+                    self.send(
+                        json!({ "jsonrpc": "2.0", "method": "$/cancel", "params": { "id": id } }),
+                    )?;
+                    return Err(Failure::Failed(if cancelled {
+                        format!("{method} cancelled")
+                    } else {
+                        format!("No answer to {method} within {timeout:?}")
+                    }));
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    let status = match self.child.try_wait() {
+                        Ok(Some(status)) => status.to_string(),
+                        _ => "closed its output".to_string(),
+                    };
+                    return Err(Failure::Crashed(format!("Plugin stopped ({status})")));
+                }
+            }
+        }
+    }
+
+    fn send(&mut self, message: Value) -> Result<(), Failure> {
+        writeln!(self.stdin, "{message}")
+            .and_then(|()| self.stdin.flush())
+            .map_err(|error| Failure::Crashed(format!("Unable to write to plugin: {error}")))
+    }
+}
+
+impl Drop for Process {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
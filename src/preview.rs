@@ -0,0 +1,239 @@
+use gtk4::prelude::*;
+use gtk4::{Box, Label, Orientation, Picture, ScrolledWindow, gio};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::calculator;
+use crate::config::Config;
+use crate::queryable::Queryable;
+
+/// How much of a text file is read for its preview
+const MAX_TEXT_BYTES: u64 = 16 * 1024;
+const MAX_TEXT_LINES: usize = 100;
+
+/// The details of an item shown in the preview pane
+pub struct Preview {
+    title: String,
+    /// Labelled values, like the Exec line of an application
+    fields: Vec<(&'static str, String)>,
+    content: Option<Content>,
+}
+
+/// A larger block shown below the fields
+enum Content {
+    Text(String),
+    Image(PathBuf),
+}
+
+/// Gathers the details of an item
+///
+/// # Arguments
+/// * `item` - The selected item
+/// * `config` - Application configuration
+///
+/// # Returns
+/// The preview of the item
+pub fn build(item: &Queryable, config: &Config) -> Preview {
+    let mut preview = Preview {
+        title: item.display_name(),
+        fields: Vec::new(),
+        content: None,
+    };
+
+    match item {
+        Queryable::Application(app) => {
+            preview.add("Description", app.description.clone());
+            preview.add_command_line(item, config);
+            preview.add("Desktop file", Some(app.path.display().to_string()));
+            preview.add(
+                "Categories",
+                Some(app.categories.join(", ")).filter(|categories| !categories.is_empty()),
+            );
+        }
+        Queryable::CustomCommand { command, .. } => {
+            preview.add("Description", command.description.clone());
+            preview.add_command_line(item, config);
+        }
+        Queryable::Calculator {
+            expression,
+            result,
+            note,
+            evaluator,
+        } => {
+            preview.add("Result", Some(result.clone()));
+            preview.add("Note", note.clone());
+            // Other sources don't go through evalexpr, so it couldn't retrace them
+            let steps = match evaluator {
+                calculator::Evaluator::Arithmetic => calculator::steps(expression, config),
+                _ => Vec::new(),
+            };
+            if !steps.is_empty() {
+                preview.content = Some(Content::Text(steps.join("\n")));
+            }
+        }
+        Queryable::SearchFallback { search_url, query } => {
+            preview.add("URL", Some(search_url.build(query)));
+        }
+        Queryable::SecondaryAction { item, .. } => {
+            let target = build(item, config);
+            preview.fields = target.fields;
+            preview.content = target.content;
+        }
+        Queryable::Error { message, .. } => {
+            preview.content = Some(Content::Text(message.clone()));
+        }
+        Queryable::Output { line } => {
+            let path = Path::new(line.trim());
+            if path.is_absolute() && path.is_file() {
+                preview.add_file(path);
+            } else {
+                preview.content = Some(Content::Text(line.clone()));
+            }
+        }
+        Queryable::Script { provider, item } => {
+            preview.add("Description", item.description.clone());
+            preview.add("Script", Some(provider.name.clone()));
+            preview.add("Command", Some(provider.command.clone()));
+        }
+        Queryable::Plugin { plugin, item } => {
+            preview.add("Description", item.description.clone());
+            preview.add("Plugin", Some(plugin.name.clone()));
+        }
+        Queryable::ProviderResult(result) => {
+            preview.add("Description", result.description.clone());
+            preview.add("Search provider", Some(result.app_name.clone()));
+        }
+        Queryable::RunnerMatch(runner_match) => {
+            preview.add("Description", runner_match.subtext.clone());
+            preview.add("Runner", Some(runner_match.runner.name.clone()));
+        }
+        Queryable::Parameter { .. }
+        | Queryable::Confirmation { .. }
+        | Queryable::Pending { .. } => {
+            preview.add("Description", item.description());
+        }
+    }
+
+    preview
+}
+
+impl Preview {
+    fn add(&mut self, label: &'static str, value: Option<String>) {
+        if let Some(value) = value {
+            self.fields.push((label, value));
+        }
+    }
+
+    fn add_command_line(&mut self, item: &Queryable, config: &Config) {
+        match item.command_line(config) {
+            Some(Ok(command_line)) => self.add("Command", Some(command_line)),
+            Some(Err(message)) => self.add("Error", Some(message)),
+            None => {}
+        }
+    }
+
+    /// Adds the path of a file, and its content if it is an image or text
+    fn add_file(&mut self, path: &Path) {
+        self.add("Path", Some(path.display().to_string()));
+
+        let (content_type, _) = gio::content_type_guess(Some(path), &[]);
+        if gio::content_type_is_mime_type(&content_type, "image/*") {
+            self.content = Some(Content::Image(path.to_path_buf()));
+        } else {
+            self.content = read_text(path).map(Content::Text);
+        }
+    }
+}
+
+/// Reads the beginning of a text file
+///
+/// # Arguments
+/// * `path` - The file to read
+///
+/// # Returns
+/// The first lines of the file, or None if it can't be read or isn't text
+fn read_text(path: &Path) -> Option<String> {
+    let mut bytes = Vec::new();
+    File::open(path)
+        .ok()?
+        .take(MAX_TEXT_BYTES)
+        .read_to_end(&mut bytes)
+        .ok()?;
+
+    if bytes.contains(&0) {
+        return None;
+    }
+
+    let text = String::from_utf8_lossy(&bytes);
+    Some(
+        text.lines()
+            .take(MAX_TEXT_LINES)
+            .collect::<Vec<_>>()
+            .join("\n"),
+    )
+}
+
+/// Creates the preview pane, hidden until an item is shown in it
+///
+/// # Arguments
+/// * `config` - Application configuration for height constraints
+///
+/// # Returns
+/// The configured ScrolledWindow widget
+pub fn create_pane(config: &Config) -> ScrolledWindow {
+    ScrolledWindow::builder()
+        .vscrollbar_policy(gtk4::PolicyType::Automatic)
+        .hscrollbar_policy(gtk4::PolicyType::Never)
+        .propagate_natural_height(true)
+        .max_content_height(config.height.saturating_sub(60).max(100))
+        .hexpand(true)
+        .visible(false)
+        .css_classes(["preview-pane"])
+        .build()
+}
+
+/// Displays a preview in the pane
+///
+/// # Arguments
+/// * `pane` - The pane made by `create_pane`
+/// * `preview` - The preview to display
+pub fn show(pane: &ScrolledWindow, preview: &Preview) {
+    let content_box = Box::builder()
+        .orientation(Orientation::Vertical)
+        .css_classes(["preview"])
+        .build();
+
+    content_box.append(&create_label(&preview.title, "preview-title"));
+    for (label, value) in &preview.fields {
+        content_box.append(&create_label(label, "preview-key"));
+        content_box.append(&create_label(value, "preview-value"));
+    }
+
+    match &preview.content {
+        Some(Content::Text(text)) => content_box.append(&create_label(text, "preview-text")),
+        Some(Content::Image(path)) => {
+            let picture = Picture::builder()
+                .file(&gio::File::for_path(path))
+                .can_shrink(true)
+                .css_classes(["preview-image"])
+                .build();
+            content_box.append(&picture);
+        }
+        None => {}
+    }
+
+    pane.set_child(Some(&content_box));
+}
+
+fn create_label(text: &str, css_class: &str) -> Label {
+    Label::builder()
+        .label(text)
+        .halign(gtk4::Align::Start)
+        .xalign(0.0)
+        .wrap(true)
+        .wrap_mode(gtk4::pango::WrapMode::WordChar)
+        .selectable(true)
+        .css_classes([css_class])
+        .build()
+}
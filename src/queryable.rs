@@ -1,10 +1,46 @@
-use log::debug;
+use gtk4::prelude::*;
+use gtk4::{gdk, gio, glib};
+use log::{debug, error};
+use std::cell::RefCell;
+use std::fmt;
+use std::io::{self, Read};
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process::{Child, ChildStderr, Command, Stdio};
+use std::rc::Rc;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use crate::{
     application::Application,
-    config::{Config, CustomCommand, SearchUrl},
+    calculator,
+    config::{
+        Config, CustomCommand, LaunchStrategy, OutputMode, Parameter, ParameterKind, Plugin,
+        ScriptProvider, SearchUrl,
+    },
+    krunner::{self, RunnerMatch},
+    plugin::{self, PluginItem},
+    script::{self, ScriptItem},
+    search_provider::{self, ProviderResult},
+    template,
 };
 
+/// How long the process may stay alive to keep serving copied text
+const CLIPBOARD_HOLD_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// How long a launched process is watched for an early failure
+const LAUNCH_GRACE_PERIOD: Duration = Duration::from_millis(300);
+const LAUNCH_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// How long the error output of a failed launch is waited for, and how much is kept
+const LAUNCH_ERROR_TIMEOUT: Duration = Duration::from_millis(100);
+const LAUNCH_ERROR_BYTES: u64 = 4096;
+
+/// How long a command with inline output may run before it is killed
+const INLINE_OUTPUT_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_OUTPUT_LINES: usize = 500;
+
 #[derive(Debug, Clone)]
 pub enum Queryable {
     Application(Application),
@@ -15,11 +51,136 @@ pub enum Queryable {
     Calculator {
         expression: String,
         result: String,
+        note: Option<String>,
+        /// The calculator source it comes from
+        evaluator: calculator::Evaluator,
     },
     SearchFallback {
         search_url: SearchUrl,
         query: String,
     },
+    SecondaryAction {
+        item: Box<Queryable>,
+        action: SecondaryAction,
+    },
+    Error {
+        title: String,
+        message: String,
+    },
+    Parameter {
+        prompt: Prompt,
+        value: String,
+        error: Option<String>,
+    },
+    Confirmation {
+        prompt: Prompt,
+        confirmed: bool,
+    },
+    Output {
+        line: String,
+    },
+    Script {
+        provider: ScriptProvider,
+        item: ScriptItem,
+    },
+    Plugin {
+        plugin: Plugin,
+        item: PluginItem,
+    },
+    ProviderResult(ProviderResult),
+    RunnerMatch(RunnerMatch),
+    /// Stands for the result of a task while it runs
+    Pending {
+        name: String,
+    },
+}
+
+/// What the launcher does once an item has been activated
+#[derive(Debug)]
+pub enum Outcome {
+    /// Close the launcher
+    Close,
+    /// Ask for the next parameter of a custom command in the entry
+    Prompt(Prompt),
+    /// Show these items in place of the search results
+    Show(Vec<Queryable>),
+    /// Go back to the search results
+    Dismiss,
+    /// Leave the entry and the list as they are
+    Stay,
+    /// Run a slow task off the main thread, then do what it returns
+    Pending(Task),
+}
+
+/// The slow part of an activation, like running a command to show its output
+pub struct Task {
+    /// The name shown while it runs: the activated item, or the program it launched
+    pub name: String,
+    run: TaskFn,
+}
+
+type TaskFn = Box<dyn FnOnce(&gio::Cancellable) -> Result<Outcome, String> + Send>;
+
+impl Task {
+    pub fn new<F>(name: &str, run: F) -> Task
+    where
+        F: FnOnce(&gio::Cancellable) -> Result<Outcome, String> + Send + 'static,
+    {
+        Task {
+            name: name.to_string(),
+            run: Box::new(run),
+        }
+    }
+
+    /// Runs the task, stopping early if it gets cancelled
+    pub fn run(self, cancellable: &gio::Cancellable) -> Result<Outcome, String> {
+        (self.run)(cancellable)
+    }
+}
+
+impl fmt::Debug for Task {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("Task")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The parameters of a custom command, asked for one after the other before it runs
+#[derive(Debug, Clone)]
+pub struct Prompt {
+    command: CustomCommand,
+    arguments: Option<String>,
+    values: Vec<(String, String)>,
+}
+
+/// Alternative ways to activate a result, offered from the secondary actions menu
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SecondaryAction {
+    RunInTerminal,
+    CopyExec,
+    OpenDesktopFile,
+    ShowContainingFolder,
+    CopyCommand,
+    CopyExpression,
+    CopySearchUrl,
+    OpenPrivateWindow,
+}
+
+impl SecondaryAction {
+    pub fn label(&self) -> &str {
+        match self {
+            SecondaryAction::RunInTerminal => "Run in terminal",
+            SecondaryAction::CopyExec => "Copy Exec line",
+            SecondaryAction::OpenDesktopFile => "Open .desktop file",
+            SecondaryAction::ShowContainingFolder => "Show containing folder",
+            SecondaryAction::CopyCommand => "Copy command",
+            SecondaryAction::CopyExpression => "Copy as expression",
+            SecondaryAction::CopySearchUrl => "Copy search URL",
+            SecondaryAction::OpenPrivateWindow => "Open in private window",
+        }
+    }
 }
 
 impl Queryable {
@@ -33,11 +194,29 @@ impl Queryable {
                     command.name.clone()
                 }
             }
-            Queryable::Calculator { expression, result } => format!("{} = {}", expression, result),
+            Queryable::Calculator {
+                expression, result, ..
+            } => format!("{} = {}", expression, result),
             Queryable::SearchFallback {
                 search_url: SearchUrl { name, .. },
                 ..
             } => format!("Search {}", name),
+            Queryable::SecondaryAction { action, .. } => action.label().to_string(),
+            Queryable::Error { title, .. } => title.clone(),
+            Queryable::Parameter { prompt, value, .. } => match prompt.parameter() {
+                Some(parameter) => format!("{}: {}", parameter.name, value),
+                None => value.clone(),
+            },
+            Queryable::Confirmation {
+                confirmed: true, ..
+            } => "Confirm".to_string(),
+            Queryable::Confirmation { .. } => "Cancel".to_string(),
+            Queryable::Output { line } => line.clone(),
+            Queryable::Script { item, .. } => item.title.clone(),
+            Queryable::Plugin { item, .. } => item.title.clone(),
+            Queryable::ProviderResult(result) => result.name.clone(),
+            Queryable::RunnerMatch(runner_match) => runner_match.text.clone(),
+            Queryable::Pending { name } => name.clone(),
         }
     }
 
@@ -54,27 +233,269 @@ impl Queryable {
                     command.description.clone()
                 }
             }
-            Queryable::Calculator { .. } => Some("Copy result to clipboard".to_string()),
+            Queryable::Calculator { note, .. } => Some(
+                note.clone()
+                    .unwrap_or_else(|| "Copy result to clipboard".to_string()),
+            ),
             Queryable::SearchFallback {
                 search_url: SearchUrl { name, .. },
                 query,
             } => Some(format!("Search '{}' on {}", query, name)),
+            Queryable::SecondaryAction { item, .. } => Some(item.display_name()),
+            Queryable::Error { message, .. } => Some(message.clone()),
+            Queryable::Parameter { prompt, error, .. } => {
+                error.clone().or_else(|| Some(prompt.progress()))
+            }
+            Queryable::Confirmation {
+                prompt,
+                confirmed: true,
+            } => Some(prompt.confirmation_message()),
+            Queryable::Confirmation { .. } => None,
+            Queryable::Output { .. } => None,
+            Queryable::Script { provider, item } => item
+                .description
+                .clone()
+                .or_else(|| Some(provider.name.clone())),
+            Queryable::Plugin { plugin, item } => item
+                .description
+                .clone()
+                .or_else(|| Some(plugin.name.clone())),
+            Queryable::ProviderResult(result) => result
+                .description
+                .clone()
+                .or_else(|| Some(result.app_name.clone())),
+            Queryable::RunnerMatch(runner_match) => runner_match
+                .subtext
+                .clone()
+                .or_else(|| Some(runner_match.runner.name.clone())),
+            Queryable::Pending { .. } => Some("Running…".to_string()),
         }
     }
 
-    pub fn action(&self, config: &Config) {
+    /// Activates the item
+    ///
+    /// # Arguments
+    /// * `config` - Application configuration
+    ///
+    /// # Returns
+    /// What to do next, or an error message if the launched process could not start
+    pub fn action(&self, config: &Config) -> Result<Outcome, String> {
         match self {
-            Queryable::Application(app) => {
-                launch_application(app, &config.terminal);
-            }
+            Queryable::Application(app) => launch_application(app, config),
             Queryable::CustomCommand { command, arguments } => {
-                execute_custom_command(command, arguments.as_deref(), &config.terminal);
+                run_custom_command(command, arguments.as_deref(), config)
             }
-            Queryable::Calculator { result, .. } => {
+            Queryable::Calculator {
+                expression,
+                result,
+                evaluator,
+                ..
+            } => {
+                if evaluator.is_remembered() {
+                    calculator::remember(expression, result);
+                }
                 copy_to_clipboard(result);
+                Ok(Outcome::Close)
             }
             Queryable::SearchFallback { search_url, query } => {
-                perform_web_search(query, search_url);
+                perform_web_search(query, search_url, config.launch_strategy)
+            }
+            Queryable::SecondaryAction { item, action } => item.secondary_action(*action, config),
+            Queryable::Error { .. } => Ok(Outcome::Close),
+            // The error is already shown, the typed value stays to be corrected
+            Queryable::Parameter { error: Some(_), .. } => Ok(Outcome::Stay),
+            Queryable::Parameter { prompt, value, .. } => {
+                prompt.answer(value.clone()).proceed(config)
+            }
+            Queryable::Confirmation {
+                prompt,
+                confirmed: true,
+            } => prompt.execute(config),
+            Queryable::Confirmation { .. } => Ok(Outcome::Dismiss),
+            Queryable::Output { line } => {
+                copy_to_clipboard(line);
+                Ok(Outcome::Close)
+            }
+            Queryable::Script { provider, item } => script::select(provider, item),
+            Queryable::Plugin { plugin, item } => plugin::activate(plugin, item),
+            Queryable::ProviderResult(result) => search_provider::activate(result),
+            Queryable::RunnerMatch(runner_match) => krunner::run(runner_match),
+            Queryable::Pending { .. } => Ok(Outcome::Dismiss),
+        }
+    }
+
+    /// Returns the name of the icon shown next to the item, if any
+    pub fn icon(&self) -> Option<&str> {
+        match self {
+            Queryable::Script { item, .. } => item.icon.as_deref(),
+            Queryable::Plugin { item, .. } => item.icon.as_deref(),
+            Queryable::ProviderResult(result) => result.icon.as_deref(),
+            Queryable::RunnerMatch(runner_match) => runner_match.icon.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Returns the command line activating the item runs, for the items running one
+    ///
+    /// # Arguments
+    /// * `config` - Application configuration for the terminal
+    ///
+    /// # Returns
+    /// The command line, or an error message if the command template can't be expanded
+    pub fn command_line(&self, config: &Config) -> Option<Result<String, String>> {
+        match self {
+            Queryable::Application(app) if app.terminal => {
+                Some(Ok(format!("{} {}", config.terminal, app.exec)))
+            }
+            Queryable::Application(app) => Some(Ok(app.exec.clone())),
+            Queryable::CustomCommand { command, arguments } => {
+                let terminal = command.tty.then_some(config.terminal.as_str());
+                Some(
+                    build_argv(command, arguments.as_deref(), &[], terminal).map(|argv| {
+                        argv.iter()
+                            .map(|word| template::quote(word))
+                            .collect::<Vec<_>>()
+                            .join(" ")
+                    }),
+                )
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the query this item completes to, for Tab completion
+    ///
+    /// # Returns
+    /// The command name followed by a space for commands accepting arguments, the
+    /// completed arguments for suggestions, the full path for output lines naming a file,
+    /// or None if the item doesn't complete the query
+    pub fn completion(&self) -> Option<String> {
+        match self {
+            Queryable::CustomCommand {
+                arguments: Some(_), ..
+            } => Some(self.display_name()),
+            Queryable::CustomCommand {
+                command,
+                arguments: None,
+            } if command.accepts_arguments => Some(format!("{} ", command.name)),
+            Queryable::Output { line } => {
+                let path = Path::new(line.trim());
+                (path.is_absolute() && path.exists()).then(|| path.display().to_string())
+            }
+            _ => None,
+        }
+    }
+
+    /// Lists the alternative actions available for this item
+    ///
+    /// # Returns
+    /// One `Queryable::SecondaryAction` per action, empty if there are none
+    pub fn secondary_actions(&self) -> Vec<Queryable> {
+        let actions = match self {
+            Queryable::Application(app) => {
+                let mut actions = vec![
+                    SecondaryAction::CopyExec,
+                    SecondaryAction::OpenDesktopFile,
+                    SecondaryAction::ShowContainingFolder,
+                ];
+                if !app.terminal {
+                    actions.insert(0, SecondaryAction::RunInTerminal);
+                }
+                actions
+            }
+            Queryable::CustomCommand { command, .. } => {
+                let mut actions = vec![SecondaryAction::CopyCommand];
+                if !command.tty {
+                    actions.insert(0, SecondaryAction::RunInTerminal);
+                }
+                actions
+            }
+            Queryable::Calculator { .. } => vec![SecondaryAction::CopyExpression],
+            Queryable::SearchFallback { .. } => vec![
+                SecondaryAction::OpenPrivateWindow,
+                SecondaryAction::CopySearchUrl,
+            ],
+            Queryable::SecondaryAction { .. }
+            | Queryable::Error { .. }
+            | Queryable::Parameter { .. }
+            | Queryable::Confirmation { .. }
+            | Queryable::Output { .. }
+            | Queryable::Script { .. }
+            | Queryable::Plugin { .. }
+            | Queryable::ProviderResult(_)
+            | Queryable::RunnerMatch(_)
+            | Queryable::Pending { .. } => Vec::new(),
+        };
+
+        actions
+            .into_iter()
+            .map(|action| Queryable::SecondaryAction {
+                item: Box::new(self.clone()),
+                action,
+            })
+            .collect()
+    }
+
+    fn secondary_action(
+        &self,
+        action: SecondaryAction,
+        config: &Config,
+    ) -> Result<Outcome, String> {
+        match (self, action) {
+            (Queryable::Application(app), SecondaryAction::RunInTerminal) => {
+                let command = format!("{} {}", config.terminal, app.exec);
+                spawn_shell(&command, vec![], config.launch_strategy, app.desktop_id())
+            }
+            (Queryable::Application(app), SecondaryAction::CopyExec) => {
+                copy_to_clipboard(&app.exec);
+                Ok(Outcome::Close)
+            }
+            (Queryable::Application(app), SecondaryAction::OpenDesktopFile) => spawn_configured(
+                &editor_command(config),
+                &app.path.to_string_lossy(),
+                config.launch_strategy,
+            ),
+            (Queryable::Application(app), SecondaryAction::ShowContainingFolder) => {
+                match app.path.parent() {
+                    Some(folder) => spawn(
+                        "xdg-open",
+                        vec![&folder.to_string_lossy()],
+                        config.launch_strategy,
+                        None,
+                    ),
+                    None => Ok(Outcome::Close),
+                }
+            }
+            (Queryable::CustomCommand { command, arguments }, SecondaryAction::RunInTerminal) => {
+                let command = CustomCommand {
+                    tty: true,
+                    ..command.clone()
+                };
+                run_custom_command(&command, arguments.as_deref(), config)
+            }
+            (Queryable::CustomCommand { command, arguments }, SecondaryAction::CopyCommand) => {
+                copy_to_clipboard(&build_command_line(command, arguments.as_deref(), &[])?);
+                Ok(Outcome::Close)
+            }
+            (Queryable::Calculator { .. }, SecondaryAction::CopyExpression) => {
+                copy_to_clipboard(&self.display_name());
+                Ok(Outcome::Close)
+            }
+            (
+                Queryable::SearchFallback { search_url, query },
+                SecondaryAction::OpenPrivateWindow,
+            ) => spawn_configured(
+                &config.private_browser,
+                &search_url.build(query),
+                config.launch_strategy,
+            ),
+            (Queryable::SearchFallback { search_url, query }, SecondaryAction::CopySearchUrl) => {
+                copy_to_clipboard(&search_url.build(query));
+                Ok(Outcome::Close)
+            }
+            _ => {
+                debug!("{:?} is not available for {}", action, self.display_name());
+                Ok(Outcome::Close)
             }
         }
     }
@@ -85,16 +506,261 @@ impl Queryable {
             Queryable::CustomCommand { .. } => ("custom-command", "description"),
             Queryable::Calculator { .. } => ("calculator-result", "description"),
             Queryable::SearchFallback { .. } => ("search-item", "description"),
+            Queryable::SecondaryAction { .. } => ("secondary-action", "description"),
+            Queryable::Error { .. } => ("error-title", "error-message"),
+            Queryable::Parameter { error: Some(_), .. } => ("parameter", "error-message"),
+            Queryable::Parameter { .. } => ("parameter", "description"),
+            Queryable::Confirmation {
+                confirmed: true, ..
+            } => ("confirm", "description"),
+            Queryable::Confirmation { .. } => ("cancel", "description"),
+            Queryable::Output { .. } => ("output-line", "description"),
+            Queryable::Script { .. } => ("script-item", "description"),
+            Queryable::Plugin { .. } => ("plugin-item", "description"),
+            Queryable::ProviderResult(_) => ("provider-result", "description"),
+            Queryable::RunnerMatch(_) => ("runner-match", "description"),
+            Queryable::Pending { .. } => ("pending", "description"),
         };
     }
 }
 
-fn launch_application(app: &Application, terminal: &str) {
+impl Prompt {
+    /// Starts asking for the parameters of a command, the typed arguments filling the
+    /// first ones
+    ///
+    /// # Arguments
+    /// * `command` - The custom command
+    /// * `arguments` - The arguments typed after the command name
+    ///
+    /// # Returns
+    /// The prompt, or an error message if the arguments can't be split into words
+    fn new(command: &CustomCommand, arguments: Option<&str>) -> Result<Prompt, String> {
+        if command.parameters.is_empty() {
+            return Ok(Prompt {
+                command: command.clone(),
+                arguments: arguments.map(str::to_string),
+                values: Vec::new(),
+            });
+        }
+
+        let mut words = template::split(arguments.unwrap_or_default())?;
+        let extra = words.split_off(words.len().min(command.parameters.len()));
+
+        let values = command
+            .parameters
+            .iter()
+            .zip(words)
+            .map(|(parameter, word)| (parameter.name.clone(), word))
+            .collect();
+        let arguments = (!extra.is_empty()).then(|| {
+            extra
+                .iter()
+                .map(|word| template::quote(word))
+                .collect::<Vec<_>>()
+                .join(" ")
+        });
+
+        Ok(Prompt {
+            command: command.clone(),
+            arguments,
+            values,
+        })
+    }
+
+    /// Returns the parameter currently asked for, None once all have a value
+    pub fn parameter(&self) -> Option<&Parameter> {
+        self.command.parameters.get(self.values.len())
+    }
+
+    /// Describes the parameter currently asked for, like `port (default: 22)`
+    pub fn label(&self) -> String {
+        let Some(parameter) = self.parameter() else {
+            return self.command.name.clone();
+        };
+
+        match &parameter.default {
+            Some(default) => format!("{} (default: {})", parameter.name, default),
+            None if parameter.kind == ParameterKind::Choice => {
+                format!("{} ({})", parameter.name, parameter.choices.join(", "))
+            }
+            None => parameter.name.clone(),
+        }
+    }
+
+    fn progress(&self) -> String {
+        format!(
+            "{} · parameter {} of {}",
+            self.command.name,
+            self.values.len() + 1,
+            self.command.parameters.len()
+        )
+    }
+
+    /// Lists the candidate values for the text typed in the entry
+    ///
+    /// # Arguments
+    /// * `text` - The entry text
+    ///
+    /// # Returns
+    /// One `Queryable::Parameter` per choice matching the text, or a single one for
+    /// the typed value, with an error if it isn't valid
+    pub fn items(&self, text: &str) -> Vec<Queryable> {
+        let Some(parameter) = self.parameter() else {
+            return Vec::new();
+        };
+
+        let text = text.trim();
+        let value = match (&parameter.default, text.is_empty()) {
+            (Some(default), true) => default.clone(),
+            _ => text.to_string(),
+        };
+
+        if parameter.kind == ParameterKind::Choice {
+            let text_lower = text.to_lowercase();
+            let choices: Vec<Queryable> = parameter
+                .choices
+                .iter()
+                .filter(|choice| choice.to_lowercase().contains(&text_lower))
+                .map(|choice| self.item(choice.clone(), None))
+                .collect();
+            if !choices.is_empty() {
+                return choices;
+            }
+        }
+
+        let error = validate(parameter, &value);
+        let value = match parameter.kind {
+            ParameterKind::Path => template::expand_home(&value),
+            _ => value,
+        };
+        vec![self.item(value, error)]
+    }
+
+    fn item(&self, value: String, error: Option<String>) -> Queryable {
+        Queryable::Parameter {
+            prompt: self.clone(),
+            value,
+            error,
+        }
+    }
+
+    fn answer(&self, value: String) -> Prompt {
+        let mut prompt = self.clone();
+        if let Some(parameter) = self.parameter() {
+            prompt.values.push((parameter.name.clone(), value));
+        }
+        prompt
+    }
+
+    /// Asks for the next parameter, then for confirmation if the command requires it,
+    /// and finally runs the command
+    fn proceed(self, config: &Config) -> Result<Outcome, String> {
+        if self.parameter().is_some() {
+            return Ok(Outcome::Prompt(self));
+        }
+
+        // Cancel comes first so that it is selected, and a repeated Enter is harmless
+        if self.command.confirm {
+            return Ok(Outcome::Show(vec![
+                Queryable::Confirmation {
+                    prompt: self.clone(),
+                    confirmed: false,
+                },
+                Queryable::Confirmation {
+                    prompt: self,
+                    confirmed: true,
+                },
+            ]));
+        }
+
+        self.execute(config)
+    }
+
+    fn confirmation_message(&self) -> String {
+        self.command
+            .confirm_message
+            .clone()
+            .unwrap_or_else(|| format!("Run {}?", self.command.name))
+    }
+
+    fn execute(&self, config: &Config) -> Result<Outcome, String> {
+        let arguments = self.arguments.as_deref();
+        if self.command.output == OutputMode::Inline {
+            let argv = build_argv(&self.command, arguments, &self.values, None)?;
+            let name = self.command.name.clone();
+            return Ok(Outcome::Pending(Task::new(
+                &name.clone(),
+                move |cancellable| {
+                    let lines = capture_output(&argv, INLINE_OUTPUT_TIMEOUT, Some(cancellable))?;
+                    if lines.is_empty() {
+                        return Err(format!("{name} printed nothing"));
+                    }
+
+                    Ok(Outcome::Show(
+                        lines
+                            .into_iter()
+                            .map(|line| Queryable::Output { line })
+                            .collect(),
+                    ))
+                },
+            )));
+        }
+
+        execute_custom_command(&self.command, arguments, &self.values, config)
+    }
+}
+
+/// Checks a parameter value against the parameter type
+///
+/// # Returns
+/// The reason the value is refused, None if it is valid
+fn validate(parameter: &Parameter, value: &str) -> Option<String> {
+    if value.is_empty() {
+        return parameter
+            .required
+            .then(|| format!("{} is required", parameter.name));
+    }
+
+    match parameter.kind {
+        ParameterKind::Number if value.parse::<f64>().is_err() => {
+            Some(format!("{value} is not a number"))
+        }
+        ParameterKind::Choice if !parameter.choices.iter().any(|choice| choice == value) => {
+            Some(format!("Expected one of {}", parameter.choices.join(", ")))
+        }
+        _ => None,
+    }
+}
+
+/// Runs a custom command, asking for its parameters and confirmation first if needed
+///
+/// # Arguments
+/// * `cmd` - The custom command
+/// * `arguments` - Optional arguments typed after the command name
+/// * `config` - Application configuration
+///
+/// # Returns
+/// The prompt for the first missing parameter, the confirmation step, or the task
+/// watching the launched process
+fn run_custom_command(
+    cmd: &CustomCommand,
+    arguments: Option<&str>,
+    config: &Config,
+) -> Result<Outcome, String> {
+    Prompt::new(cmd, arguments)?.proceed(config)
+}
+
+fn launch_application(app: &Application, config: &Config) -> Result<Outcome, String> {
     if app.terminal {
-        let command_name = format!("{} {}", terminal, app.exec);
-        spawn_shell(&command_name, vec![]);
+        let command_name = format!("{} {}", config.terminal, app.exec);
+        spawn_shell(
+            &command_name,
+            vec![],
+            config.launch_strategy,
+            app.desktop_id(),
+        )
     } else {
-        spawn_shell(&app.exec, vec![]);
+        spawn_shell(&app.exec, vec![], config.launch_strategy, app.desktop_id())
     }
 }
 
@@ -103,9 +769,14 @@ fn launch_application(app: &Application, terminal: &str) {
 /// # Arguments
 /// * `query` - The search query
 /// * `search_url_template` - URL template with {q} placeholder
-fn perform_web_search(query: &str, search_url: &SearchUrl) {
+/// * `strategy` - How the browser process is started
+fn perform_web_search(
+    query: &str,
+    search_url: &SearchUrl,
+    strategy: LaunchStrategy,
+) -> Result<Outcome, String> {
     let search_url = search_url.build(query);
-    spawn("xdg-open", vec![&search_url]);
+    spawn("xdg-open", vec![&search_url], strategy, None)
 }
 
 /// Copies text to the system clipboard
@@ -113,46 +784,477 @@ fn perform_web_search(query: &str, search_url: &SearchUrl) {
 /// # Arguments
 /// * `text` - The text to copy
 fn copy_to_clipboard(text: &str) {
-    spawn_shell(
-        &format!("echo -n '{}' | xclip -selection clipboard", text),
-        vec![],
-    );
+    let Some(display) = gdk::Display::default() else {
+        error!("No display available to copy to the clipboard");
+        return;
+    };
+
+    let clipboard = display.clipboard();
+    clipboard.set_text(text);
+    hold_until_clipboard_released(&clipboard);
+}
+
+/// Keeps the application alive while it owns the clipboard, so the copied text
+/// is still served after the window closes until another client (usually a
+/// clipboard manager) takes ownership
+///
+/// # Arguments
+/// * `clipboard` - The clipboard the text was copied to
+fn hold_until_clipboard_released(clipboard: &gdk::Clipboard) {
+    let Some(application) = gio::Application::default() else {
+        return;
+    };
+
+    let guard = Rc::new(RefCell::new(Some(application.hold())));
+    let handler_id = Rc::new(RefCell::new(None));
+
+    let release = {
+        let guard = guard.clone();
+        let handler_id = handler_id.clone();
+        let clipboard = clipboard.clone();
+        move || {
+            if let Some(handler_id) = handler_id.borrow_mut().take() {
+                clipboard.disconnect(handler_id);
+            }
+            guard.borrow_mut().take();
+        }
+    };
+
+    handler_id.replace(Some(clipboard.connect_changed({
+        let release = release.clone();
+        move |clipboard| {
+            if !clipboard.is_local() {
+                debug!("Clipboard ownership handed over");
+                release();
+            }
+        }
+    })));
+
+    glib::timeout_add_local_once(CLIPBOARD_HOLD_TIMEOUT, release);
 }
 
 /// Executes a custom command with optional arguments
 ///
+/// The argv is built from the command template unless the command opts into the shell
+///
 /// # Arguments
 /// * `cmd` - The custom command to execute
 /// * `arguments` - Optional arguments to pass to the command
-/// * `terminal` - Terminal command to use when tty is true
-fn execute_custom_command(cmd: &CustomCommand, arguments: Option<&str>, terminal: &str) {
-    let command_to_run = if let Some(args) = arguments {
-        format!("{} {}", cmd.command, args)
-    } else {
-        cmd.command.clone()
+/// * `values` - The parameter values replacing `{name}` placeholders
+/// * `config` - Application configuration for the terminal and launch strategy
+fn execute_custom_command(
+    cmd: &CustomCommand,
+    arguments: Option<&str>,
+    values: &[(String, String)],
+    config: &Config,
+) -> Result<Outcome, String> {
+    let terminal = cmd.tty.then_some(config.terminal.as_str());
+    let argv = build_argv(cmd, arguments, values, terminal)?;
+
+    let Some((program, arguments)) = argv.split_first() else {
+        return Err(format!("{} has an empty command", cmd.name));
     };
+    spawn(
+        program,
+        arguments.iter().map(String::as_str).collect(),
+        config.launch_strategy,
+        None,
+    )
+}
+
+/// Builds the argv of a custom command, going through `sh -c` if it opts into the shell
+///
+/// # Arguments
+/// * `cmd` - The custom command
+/// * `arguments` - Optional arguments to pass to the command
+/// * `values` - The parameter values replacing `{name}` placeholders
+/// * `terminal` - Terminal command to run the command in, if any
+///
+/// # Returns
+/// The argv, or an error message if the template can't be expanded
+fn build_argv(
+    cmd: &CustomCommand,
+    arguments: Option<&str>,
+    values: &[(String, String)],
+    terminal: Option<&str>,
+) -> Result<Vec<String>, String> {
+    if cmd.shell {
+        let command_to_run = build_command_line(cmd, arguments, values)?;
+
+        let final_command = if let Some(terminal) = terminal {
+            format!("{} {}", terminal, command_to_run)
+        } else {
+            command_to_run
+        };
+
+        return Ok(vec!["sh".to_string(), "-c".to_string(), final_command]);
+    }
+
+    let words = template::substitute(&cmd.command.words()?, values);
+    let mut argv = template::expand(&words, arguments)?;
+    if let Some(terminal) = terminal {
+        let mut terminal = template::split(terminal)?;
+        terminal.extend(argv);
+        argv = terminal;
+    }
+
+    Ok(argv)
+}
 
-    let final_command = if cmd.tty {
-        format!("{} {}", terminal, command_to_run)
+/// Runs a command to completion and collects its output lines
+///
+/// # Arguments
+/// * `argv` - The program and its arguments
+/// * `timeout` - How long the command may run before it is killed
+/// * `cancellable` - Kills the command when cancelled
+///
+/// # Returns
+/// The non-empty stdout lines, or an error message with stderr if the command fails,
+/// runs longer than `timeout` or is cancelled
+pub fn capture_output(
+    argv: &[String],
+    timeout: Duration,
+    cancellable: Option<&gio::Cancellable>,
+) -> Result<Vec<String>, String> {
+    let Some((program, arguments)) = argv.split_first() else {
+        return Err("Empty command".to_string());
+    };
+    debug!("Capturing output of {program} {arguments:?}");
+
+    let mut child = Command::new(program)
+        .args(arguments)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| format!("Unable to run {program}: {error}"))?;
+
+    let readers: Vec<_> = [
+        child
+            .stdout
+            .take()
+            .map(|pipe| Box::new(pipe) as Box<dyn Read + Send>),
+        child
+            .stderr
+            .take()
+            .map(|pipe| Box::new(pipe) as Box<dyn Read + Send>),
+    ]
+    .into_iter()
+    .map(|pipe| {
+        thread::spawn(move || {
+            let mut output = String::new();
+            if let Some(mut pipe) = pipe {
+                let _ = pipe.read_to_string(&mut output);
+            }
+            output
+        })
+    })
+    .collect();
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if cancellable.is_some_and(|cancellable| cancellable.is_cancelled()) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("{program} cancelled"));
+            }
+            Ok(None) if Instant::now() < deadline => thread::sleep(LAUNCH_POLL_INTERVAL),
+            Ok(None) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!(
+                    "{program} took longer than {}s",
+                    timeout.as_secs_f32()
+                ));
+            }
+            Err(error) => return Err(format!("Unable to wait for {program}: {error}")),
+        }
+    };
+
+    let mut outputs = readers
+        .into_iter()
+        .map(|reader| reader.join().unwrap_or_default());
+    let stdout = outputs.next().unwrap_or_default();
+    let stderr = outputs.next().unwrap_or_default();
+
+    if !status.success() {
+        let stderr = stderr.trim();
+        return Err(if stderr.is_empty() {
+            format!("{program} failed ({status})")
+        } else {
+            stderr.to_string()
+        });
+    }
+
+    Ok(stdout
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.trim().is_empty())
+        .take(MAX_OUTPUT_LINES)
+        .map(str::to_string)
+        .collect())
+}
+
+/// Builds the command line of a custom command with its optional arguments
+///
+/// # Arguments
+/// * `cmd` - The custom command
+/// * `arguments` - Optional arguments to pass to the command
+/// * `values` - The parameter values replacing `{name}` placeholders
+///
+/// # Returns
+/// The command line, without the terminal prefix, or an error message if the
+/// template can't be expanded
+fn build_command_line(
+    cmd: &CustomCommand,
+    arguments: Option<&str>,
+    values: &[(String, String)],
+) -> Result<String, String> {
+    if !cmd.shell {
+        let words = template::substitute(&cmd.command.words()?, values);
+        let argv = template::expand(&words, arguments)?;
+        return Ok(argv
+            .iter()
+            .map(|word| template::quote(word))
+            .collect::<Vec<_>>()
+            .join(" "));
+    }
+
+    let line = template::substitute_line(&cmd.command.line(), values);
+    Ok(if let Some(args) = arguments {
+        format!("{} {}", line, args)
     } else {
-        command_to_run.clone()
+        line
+    })
+}
+
+/// Runs a configured program (like `firefox --private-window`) with one extra argument
+///
+/// # Arguments
+/// * `command` - The configured command, split on whitespace
+/// * `argument` - The argument appended to the command
+/// * `strategy` - How the process is started
+fn spawn_configured(
+    command: &str,
+    argument: &str,
+    strategy: LaunchStrategy,
+) -> Result<Outcome, String> {
+    let mut parts = command.split_whitespace();
+    let Some(program) = parts.next() else {
+        return Err("No command configured".to_string());
     };
 
-    spawn_shell(&final_command, vec![]);
+    let mut arguments: Vec<&str> = parts.collect();
+    arguments.push(argument);
+    spawn(program, arguments, strategy, None)
 }
 
-fn spawn_shell(command: &str, arguments: Vec<&str>) {
+/// Returns the command opening a file for editing
+///
+/// # Arguments
+/// * `config` - Application configuration
+///
+/// # Returns
+/// The configured editor, or else `$VISUAL`, `$EDITOR` or `vi` run in the terminal
+fn editor_command(config: &Config) -> String {
+    if let Some(editor) = &config.editor {
+        return editor.clone();
+    }
+
+    let editor = ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|variable| std::env::var(variable).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string());
+    format!("{} {}", config.terminal, editor)
+}
+
+fn spawn_shell(
+    command: &str,
+    arguments: Vec<&str>,
+    strategy: LaunchStrategy,
+    unit_id: Option<&str>,
+) -> Result<Outcome, String> {
     let mut spawning_arguments = vec!["-c", command];
     spawning_arguments.extend(arguments);
-    spawn("sh", spawning_arguments)
+    spawn("sh", spawning_arguments, strategy, unit_id)
 }
 
-fn spawn(command_name: &str, arguments: Vec<&str>) {
-    debug!("Spawing {command_name} {arguments:?}");
-    let _ = std::process::Command::new(command_name)
+/// Spawns a detached process, then watches it for an early failure off the main thread
+///
+/// Stderr goes to a pipe the watcher reads, keeping the beginning for the error message
+/// and discarding the rest. The process ignores SIGPIPE so that it isn't killed by
+/// writing to stderr once the launcher has exited and the pipe has no reader left.
+///
+/// # Arguments
+/// * `command_name` - The program to run
+/// * `arguments` - Its arguments
+/// * `strategy` - How the process is started
+/// * `unit_id` - Desktop file ID naming the systemd scope, if any
+///
+/// # Returns
+/// A task named after the launched program, closing the launcher once the process has
+/// outlived `LAUNCH_GRACE_PERIOD` and failing if it exits unsuccessfully before, or an
+/// error message if the process can't be spawned
+fn spawn(
+    command_name: &str,
+    arguments: Vec<&str>,
+    strategy: LaunchStrategy,
+    unit_id: Option<&str>,
+) -> Result<Outcome, String> {
+    debug!("Spawing {command_name} {arguments:?} ({strategy:?})");
+    let command_line = format!("{command_name} {}", arguments.join(" "));
+    let name = program_name(command_name, &arguments);
+
+    let mut command = match strategy {
+        LaunchStrategy::Direct => Command::new(command_name),
+        LaunchStrategy::Setsid => {
+            let mut command = Command::new("setsid");
+            command.arg(command_name);
+            command
+        }
+        LaunchStrategy::SystemdScope => {
+            let mut command = Command::new("systemd-run");
+            command.args(["--user", "--scope", "--quiet"]);
+            if let Some(unit_id) = unit_id {
+                command.arg(format!("--unit={}", scope_name(unit_id)));
+            }
+            command.arg("--").arg(command_name);
+            command
+        }
+    };
+
+    command
         .args(&arguments)
-        .stdin(std::process::Stdio::null())
-        .stdout(std::process::Stdio::null())
-        .stderr(std::process::Stdio::null())
-        .spawn();
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped());
+    // SAFETY: the closure runs in the forked child before exec and only calls signal(),
+    // which is async-signal-safe
+    unsafe {
+        command.pre_exec(|| {
+            libc::signal(libc::SIGPIPE, libc::SIG_IGN);
+            Ok(())
+        });
+    }
+
+    let mut child = command.spawn().map_err(|error| {
+        let message = format!("Unable to run {command_name}: {error}");
+        error!("Launching {command_line} failed: {message}");
+        message
+    })?;
+    let stderr = child.stderr.take();
+
+    Ok(Outcome::Pending(Task::new(&name, move |cancellable| {
+        watch_launch(child, stderr, &command_line, cancellable)
+            .inspect_err(|message| error!("Launching {command_line} failed: {message}"))
+    })))
+}
+
+/// Returns the name of the program a launch runs, the one `sh -c` runs for shell commands
+fn program_name(command_name: &str, arguments: &[&str]) -> String {
+    let program = match (command_name, arguments) {
+        ("sh", ["-c", command, ..]) => command.split_whitespace().next().unwrap_or("sh"),
+        _ => command_name,
+    };
+
+    Path::new(program).file_name().map_or_else(
+        || program.to_string(),
+        |name| name.to_string_lossy().to_string(),
+    )
+}
+
+/// Waits for a launched process to either exit or outlive `LAUNCH_GRACE_PERIOD`
+///
+/// # Arguments
+/// * `child` - The launched process
+/// * `stderr` - The read end of its stderr pipe
+/// * `command_line` - The command line, for the error message
+/// * `cancellable` - Stops watching when the launcher moves on
+///
+/// # Returns
+/// `Outcome::Close`, or what the process wrote to stderr if it failed
+fn watch_launch(
+    mut child: Child,
+    stderr: Option<ChildStderr>,
+    command_line: &str,
+    cancellable: &gio::Cancellable,
+) -> Result<Outcome, String> {
+    let (sender, receiver) = mpsc::channel();
+    if let Some(stderr) = stderr {
+        thread::spawn(move || read_error_output(stderr, sender));
+    }
+
+    let deadline = Instant::now() + LAUNCH_GRACE_PERIOD;
+    while Instant::now() < deadline && !cancellable.is_cancelled() {
+        match child.try_wait() {
+            Ok(Some(status)) if !status.success() => {
+                // The read gives up in case a process it started keeps the pipe open
+                let output = receiver
+                    .recv_timeout(LAUNCH_ERROR_TIMEOUT)
+                    .unwrap_or_default();
+                let output = output.trim();
+                return Err(if output.is_empty() {
+                    format!("{command_line} failed ({status})")
+                } else {
+                    output.to_string()
+                });
+            }
+            Ok(Some(_)) | Err(_) => break,
+            Ok(None) => thread::sleep(LAUNCH_POLL_INTERVAL),
+        }
+    }
+
+    Ok(Outcome::Close)
+}
+
+/// Reads the stderr pipe of a launched process until it is closed
+///
+/// The beginning of the output is sent as soon as it is read or the pipe is closed,
+/// the rest is discarded so that the process never blocks on a full pipe.
+///
+/// # Arguments
+/// * `stderr` - The read end of the stderr pipe
+/// * `sender` - Receives the beginning of the output
+fn read_error_output(mut stderr: ChildStderr, sender: mpsc::Sender<String>) {
+    let mut output = Vec::new();
+    let _ = (&mut stderr)
+        .take(LAUNCH_ERROR_BYTES)
+        .read_to_end(&mut output);
+    let _ = sender.send(String::from_utf8_lossy(&output).to_string());
+    let _ = io::copy(&mut stderr, &mut io::sink());
+}
+
+/// Builds a transient scope name following the `app-<launcher>-<id>-<random>.scope`
+/// convention, so desktops and `systemctl --user` can tell which app runs in it
+///
+/// # Arguments
+/// * `unit_id` - The desktop file ID of the launched application
+///
+/// # Returns
+/// The unit name, with characters systemd doesn't allow escaped
+fn scope_name(unit_id: &str) -> String {
+    let escaped: String = unit_id
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '.' | ':' => c.to_string(),
+            _ => c
+                .to_string()
+                .bytes()
+                .map(|byte| format!("\\x{byte:02x}"))
+                .collect(),
+        })
+        .collect();
+
+    format!("app-palet-{escaped}-{}.scope", unique_suffix())
+}
+
+fn unique_suffix() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    format!("{:x}", nanos & 0xffff_ffff)
 }
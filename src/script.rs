@@ -0,0 +1,159 @@
+use gtk4::gio;
+use gtk4::prelude::*;
+use log::{debug, error};
+use serde::Deserialize;
+use std::time::Duration;
+
+use crate::config::{ScriptProvider, ScriptProviders};
+use crate::queryable::{self, Outcome, Queryable, Task};
+use crate::template;
+
+/// How long a script may take to answer a query before its results are dropped
+const QUERY_TIMEOUT: Duration = Duration::from_secs(2);
+/// How long a script may take to handle a selection
+const SELECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A result row printed by a script, one JSON object per line
+#[derive(Deserialize, Clone, Debug)]
+pub struct ScriptItem {
+    pub title: String,
+    pub description: Option<String>,
+    pub icon: Option<String>,
+    /// Passed back to the script on selection, the title if missing
+    pub action: Option<String>,
+}
+
+/// Asks the script providers matching the query for result rows
+///
+/// Providers with a prefix only answer queries starting with it, the others answer
+/// every query.
+///
+/// # Arguments
+/// * `providers` - The configured script providers
+/// * `query` - The search query
+/// * `cancellable` - Kills the running scripts when the query is outdated
+///
+/// # Returns
+/// The rows printed by the scripts, prefixed providers first
+pub fn query(
+    providers: &ScriptProviders,
+    query: &str,
+    cancellable: &gio::Cancellable,
+) -> Vec<Queryable> {
+    let mut prefixed = Vec::new();
+    let mut others = Vec::new();
+
+    for provider in providers.values() {
+        match &provider.prefix {
+            Some(prefix) => {
+                if let Some(rest) = strip_prefix(query, prefix) {
+                    prefixed.extend(run_query(provider, rest, cancellable));
+                }
+            }
+            None => others.extend(run_query(provider, query, cancellable)),
+        }
+    }
+
+    prefixed.extend(others);
+    prefixed
+}
+
+/// Calls the script back with the selected row, off the main thread
+///
+/// # Arguments
+/// * `provider` - The provider the row comes from
+/// * `item` - The selected row
+///
+/// # Returns
+/// The task running the script, which shows the rows it printed in answer as a new
+/// list, or closes the launcher if it printed nothing
+pub fn select(provider: &ScriptProvider, item: &ScriptItem) -> Result<Outcome, String> {
+    let action = item.action.as_deref().unwrap_or(&item.title);
+    let argv = build_argv(provider, &["--select", action])?;
+    let provider = provider.clone();
+
+    Ok(Outcome::Pending(Task::new(
+        &item.title,
+        move |cancellable| {
+            let items = parse_items(
+                &provider,
+                queryable::capture_output(&argv, SELECT_TIMEOUT, Some(cancellable))?,
+            );
+            if items.is_empty() {
+                Ok(Outcome::Close)
+            } else {
+                Ok(Outcome::Show(items))
+            }
+        },
+    )))
+}
+
+fn run_query(
+    provider: &ScriptProvider,
+    query: &str,
+    cancellable: &gio::Cancellable,
+) -> Vec<Queryable> {
+    let lines = build_argv(provider, &[query])
+        .and_then(|argv| queryable::capture_output(&argv, QUERY_TIMEOUT, Some(cancellable)));
+
+    match lines {
+        Ok(lines) => parse_items(provider, lines),
+        Err(_) if cancellable.is_cancelled() => Vec::new(),
+        Err(message) => {
+            error!("Script provider {} failed: {message}", provider.name);
+            Vec::new()
+        }
+    }
+}
+
+fn build_argv(provider: &ScriptProvider, arguments: &[&str]) -> Result<Vec<String>, String> {
+    let mut argv = template::split(&provider.command)?;
+    if let Some(program) = argv.first_mut() {
+        *program = template::expand_home(program);
+    }
+    argv.extend(arguments.iter().map(|argument| argument.to_string()));
+    Ok(argv)
+}
+
+/// Parses script output, lines that aren't JSON objects becoming plain titles
+fn parse_items(provider: &ScriptProvider, lines: Vec<String>) -> Vec<Queryable> {
+    lines
+        .into_iter()
+        .map(|line| {
+            let item = serde_json::from_str::<ScriptItem>(&line).unwrap_or_else(|error| {
+                debug!("Using {line:?} as a plain title: {error}");
+                ScriptItem {
+                    title: line,
+                    description: None,
+                    icon: None,
+                    action: None,
+                }
+            });
+            Queryable::Script {
+                provider: provider.clone(),
+                item,
+            }
+        })
+        .collect()
+}
+
+/// Removes a provider prefix and the space after it from a query
+///
+/// # Arguments
+/// * `query` - The search query
+/// * `prefix` - The prefix, matched regardless of case
+///
+/// # Returns
+/// The rest of the query, or `None` if it doesn't start with the prefix
+pub fn strip_prefix<'a>(query: &'a str, prefix: &str) -> Option<&'a str> {
+    let rest = query.get(prefix.len()..)?;
+    if !query[..prefix.len()].eq_ignore_ascii_case(prefix) {
+        return None;
+    }
+
+    match rest.strip_prefix(' ') {
+        Some(rest) => Some(rest),
+        None if rest.is_empty() => Some(rest),
+        None => None,
+    }
+}
@@ -0,0 +1,200 @@
+use gtk4::prelude::*;
+use gtk4::{gio, glib};
+use log::error;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::application::{self, Application};
+use crate::config::{Completion, Config};
+use crate::queryable::Queryable;
+use crate::{krunner, plugin, script, search_provider};
+
+/// A source of results slow enough to be asked off the main thread
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Provider {
+    Completions,
+    Scripts,
+    Plugins,
+    SearchProviders,
+    KRunner,
+}
+
+impl Provider {
+    /// Every provider, in the order their results are listed
+    const ALL: [Provider; 5] = [
+        Provider::Completions,
+        Provider::Scripts,
+        Provider::Plugins,
+        Provider::SearchProviders,
+        Provider::KRunner,
+    ];
+
+    /// How long typing must pause before the provider is asked, so scripts aren't
+    /// spawned and D-Bus services aren't called for every keystroke
+    fn debounce(self) -> Duration {
+        match self {
+            // Completion commands run once, later queries are answered from their output
+            Provider::Completions => Duration::ZERO,
+            Provider::Scripts => Duration::from_millis(150),
+            // Plugins keep running and drop outdated queries themselves
+            Provider::Plugins => Duration::ZERO,
+            Provider::SearchProviders | Provider::KRunner => Duration::from_millis(100),
+        }
+    }
+
+    fn is_enabled(self, config: &Config) -> bool {
+        match self {
+            Provider::Completions => config
+                .custom_commands
+                .values()
+                .any(|command| matches!(command.completion, Some(Completion::Command(_)))),
+            Provider::Scripts => !config.scripts.is_empty(),
+            Provider::Plugins => !config.plugins.is_empty(),
+            Provider::SearchProviders => config.search_providers.enabled,
+            Provider::KRunner => config.krunner.enabled,
+        }
+    }
+
+    fn query(
+        self,
+        apps: &[Application],
+        config: &Config,
+        query: &str,
+        cancellable: &gio::Cancellable,
+    ) -> Vec<Queryable> {
+        match self {
+            Provider::Completions => {
+                application::command_suggestions(&config.custom_commands, query)
+            }
+            Provider::Scripts => script::query(&config.scripts, query, cancellable),
+            Provider::Plugins => plugin::query(&config.plugins, query, cancellable),
+            Provider::SearchProviders => {
+                search_provider::query(apps, &config.search_providers, query, cancellable)
+            }
+            Provider::KRunner => krunner::query(&config.krunner, query, cancellable),
+        }
+    }
+}
+
+/// The items listed for a query: the results of the synchronous sources first, then
+/// those of each provider as it answers
+///
+/// Provider results go after the rows already listed, before the web search fallback,
+/// so rows on screen and the selection don't move when they arrive.
+#[derive(Default)]
+pub struct Results {
+    items: Vec<Queryable>,
+    /// Number of rows before the web search fallback, where provider results start
+    local: usize,
+    /// The providers that answered, with the number of rows each added
+    answered: Vec<(Provider, usize)>,
+}
+
+impl Results {
+    /// Starts the results of a query with those of the synchronous sources
+    pub fn new(items: Vec<Queryable>) -> Results {
+        let local = items
+            .iter()
+            .take_while(|item| !matches!(item, Queryable::SearchFallback { .. }))
+            .count();
+
+        Results {
+            items,
+            local,
+            answered: Vec::new(),
+        }
+    }
+
+    pub fn items(&self) -> &[Queryable] {
+        &self.items
+    }
+
+    /// Whether the providers should be asked too, a calculation answering the query alone
+    pub fn wants_providers(&self) -> bool {
+        !matches!(self.items.first(), Some(Queryable::Calculator { .. }))
+    }
+
+    /// Adds the results of a provider after those of the providers listed before it
+    ///
+    /// # Arguments
+    /// * `provider` - The provider that answered
+    /// * `items` - Its results
+    ///
+    /// # Returns
+    /// The position of the first added item
+    pub fn insert(&mut self, provider: Provider, items: Vec<Queryable>) -> usize {
+        let position = self.local
+            + self
+                .answered
+                .iter()
+                .filter(|(answered, _)| *answered < provider)
+                .map(|(_, count)| count)
+                .sum::<usize>();
+
+        self.answered.push((provider, items.len()));
+        self.items.splice(position..position, items);
+        position
+    }
+}
+
+/// Asks the enabled providers for results off the main thread, each once its debounce
+/// delay has passed
+///
+/// # Arguments
+/// * `apps` - The installed applications
+/// * `config` - Application configuration
+/// * `query` - The search query
+/// * `on_results` - Called on the main thread with the results of each provider, unless
+///   the query was cancelled in the meantime
+///
+/// # Returns
+/// The cancellable to cancel once the query is outdated, stopping the pending providers
+pub fn query_providers<F>(
+    apps: &Arc<Vec<Application>>,
+    config: &Arc<Config>,
+    query: &str,
+    on_results: F,
+) -> gio::Cancellable
+where
+    F: Fn(Provider, Vec<Queryable>) + Clone + 'static,
+{
+    let cancellable = gio::Cancellable::new();
+    if query.trim().is_empty() {
+        return cancellable;
+    }
+
+    for provider in Provider::ALL {
+        if !provider.is_enabled(config) {
+            continue;
+        }
+
+        let apps = apps.clone();
+        let config = config.clone();
+        let query = query.to_string();
+        let cancellable = cancellable.clone();
+        let on_results = on_results.clone();
+
+        glib::spawn_future_local(async move {
+            glib::timeout_future(provider.debounce()).await;
+            if cancellable.is_cancelled() {
+                return;
+            }
+
+            let worker_cancellable = cancellable.clone();
+            let items = gio::spawn_blocking(move || {
+                provider.query(&apps, &config, &query, &worker_cancellable)
+            })
+            .await
+            .unwrap_or_else(|_| {
+                error!("The {provider:?} provider panicked");
+                Vec::new()
+            });
+
+            if !cancellable.is_cancelled() && !items.is_empty() {
+                on_results(provider, items);
+            }
+        });
+    }
+
+    cancellable
+}
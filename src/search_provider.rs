@@ -0,0 +1,250 @@
+use gtk4::prelude::*;
+use gtk4::{gio, glib};
+use log::{debug, error, info};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::application::Application;
+use crate::config::SearchProviders;
+use crate::dbus::{self, Service};
+use crate::queryable::{Outcome, Queryable, Task};
+
+const INTERFACE: &str = "org.gnome.Shell.SearchProvider2";
+const SECTION: &str = "Shell Search Provider";
+
+/// How long a search provider may take to answer a call, in milliseconds
+const QUERY_TIMEOUT_MS: i32 = 500;
+const ACTIVATE_TIMEOUT_MS: i32 = 5000;
+
+/// How many results of each provider are shown
+const MAX_RESULTS: usize = 5;
+
+/// Search providers declared by installed apps, discovered once per launcher session
+static PROVIDERS: OnceLock<Vec<SearchProvider>> = OnceLock::new();
+
+/// A search provider declared in a `gnome-shell/search-providers/*.ini` file
+#[derive(Debug, Clone)]
+pub struct SearchProvider {
+    pub desktop_id: String,
+    pub bus_name: String,
+    pub object_path: String,
+}
+
+/// A result returned by a search provider
+#[derive(Debug, Clone)]
+pub struct ProviderResult {
+    pub provider: SearchProvider,
+    /// Name of the app owning the provider
+    pub app_name: String,
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub icon: Option<String>,
+    /// The search terms the result was found for, passed back on activation
+    pub terms: Vec<String>,
+}
+
+/// Asks the search providers of installed apps for results over the session bus
+///
+/// # Arguments
+/// * `apps` - The installed applications, used to name the providers
+/// * `settings` - Which providers are enabled
+/// * `query` - The search query
+/// * `cancellable` - Cancels the pending calls when the query is outdated
+///
+/// # Returns
+/// The first results of every provider answering in time, all providers being asked at once
+pub fn query(
+    apps: &[Application],
+    settings: &SearchProviders,
+    query: &str,
+    cancellable: &gio::Cancellable,
+) -> Vec<Queryable> {
+    let terms: Vec<String> = query.split_whitespace().map(str::to_string).collect();
+    if !settings.enabled || terms.is_empty() {
+        return Vec::new();
+    }
+
+    if dbus::session_bus().is_none() {
+        return Vec::new();
+    }
+
+    let providers: Vec<&SearchProvider> = providers()
+        .iter()
+        .filter(|provider| !settings.disabled.contains(&provider.desktop_id))
+        .collect();
+    let answers = dbus::call_each(&providers, |provider| {
+        provider_results(provider, &terms, cancellable)
+    });
+
+    let mut results = Vec::new();
+    for (provider, answer) in providers.into_iter().zip(answers) {
+        match answer {
+            Ok(metas) => {
+                let app_name = apps
+                    .iter()
+                    .find(|app| {
+                        app.desktop_id()
+                            .is_some_and(|id| provider.desktop_id == format!("{id}.desktop"))
+                    })
+                    .map_or_else(|| provider.desktop_id.clone(), |app| app.name.clone());
+
+                results.extend(metas.into_iter().filter_map(|meta| {
+                    parse_meta(provider, &app_name, &terms, meta).map(Queryable::ProviderResult)
+                }));
+            }
+            Err(message) => debug!("{message}"),
+        }
+    }
+
+    results
+}
+
+/// Opens a result in the app owning its search provider
+///
+/// # Arguments
+/// * `result` - The result to activate
+///
+/// # Returns
+/// A task calling the provider off the main thread, the launcher closing once it answers
+pub fn activate(result: &ProviderResult) -> Result<Outcome, String> {
+    let result = result.clone();
+
+    Ok(Outcome::Pending(Task::new(
+        &result.name.clone(),
+        move |cancellable| {
+            dbus::call::<()>(
+                &result.provider.service(),
+                "ActivateResult",
+                (result.id.as_str(), &result.terms, 0u32).to_variant(),
+                ACTIVATE_TIMEOUT_MS,
+                Some(cancellable),
+            )
+            .map(|()| Outcome::Close)
+        },
+    )))
+}
+
+fn provider_results(
+    provider: &SearchProvider,
+    terms: &[String],
+    cancellable: &gio::Cancellable,
+) -> Result<Vec<HashMap<String, glib::Variant>>, String> {
+    let service = provider.service();
+    let (mut ids,) = dbus::call::<(Vec<String>,)>(
+        &service,
+        "GetInitialResultSet",
+        (terms,).to_variant(),
+        QUERY_TIMEOUT_MS,
+        Some(cancellable),
+    )?;
+    ids.truncate(MAX_RESULTS);
+
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let (metas,) = dbus::call::<(Vec<HashMap<String, glib::Variant>>,)>(
+        &service,
+        "GetResultMetas",
+        (ids,).to_variant(),
+        QUERY_TIMEOUT_MS,
+        Some(cancellable),
+    )?;
+    Ok(metas)
+}
+
+impl SearchProvider {
+    fn service(&self) -> Service<'_> {
+        Service {
+            bus_name: &self.bus_name,
+            object_path: &self.object_path,
+            interface: INTERFACE,
+        }
+    }
+}
+
+fn parse_meta(
+    provider: &SearchProvider,
+    app_name: &str,
+    terms: &[String],
+    meta: HashMap<String, glib::Variant>,
+) -> Option<ProviderResult> {
+    let string = |key: &str| meta.get(key).and_then(|value| value.get::<String>());
+
+    let icon = string("gicon").or_else(|| {
+        let icon = gio::Icon::deserialize(meta.get("icon")?)?;
+        match icon.downcast_ref::<gio::ThemedIcon>() {
+            Some(themed) => themed.names().first().map(|name| name.to_string()),
+            None => icon.to_string().map(|icon| icon.to_string()),
+        }
+    });
+
+    Some(ProviderResult {
+        provider: provider.clone(),
+        app_name: app_name.to_string(),
+        id: string("id")?,
+        name: string("name")?,
+        description: string("description").filter(|description| !description.is_empty()),
+        icon,
+        terms: terms.to_vec(),
+    })
+}
+
+fn providers() -> &'static [SearchProvider] {
+    PROVIDERS.get_or_init(discover_providers)
+}
+
+/// Reads the search provider files of every data directory, the user's first
+fn discover_providers() -> Vec<SearchProvider> {
+    let mut data_dirs = vec![glib::user_data_dir()];
+    data_dirs.extend(glib::system_data_dirs());
+
+    let mut providers: Vec<SearchProvider> = Vec::new();
+    for data_dir in data_dirs {
+        let Ok(entries) = fs::read_dir(data_dir.join("gnome-shell/search-providers")) else {
+            continue;
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_none_or(|extension| extension != "ini") {
+                continue;
+            }
+            if let Some(provider) = parse_provider_file(&path)
+                && !providers
+                    .iter()
+                    .any(|known| known.desktop_id == provider.desktop_id)
+            {
+                providers.push(provider);
+            }
+        }
+    }
+
+    info!("Found {} search providers", providers.len());
+    providers
+}
+
+fn parse_provider_file(path: &Path) -> Option<SearchProvider> {
+    let entry = freedesktop_entry_parser::parse_entry(path)
+        .inspect_err(|error| error!("Unable to read {}: {error}", path.display()))
+        .ok()?;
+    let section = entry.section(SECTION);
+
+    if section.attr("Version") != Some("2") {
+        debug!("{} is not a version 2 search provider", path.display());
+        return None;
+    }
+    if section.attr("DefaultDisabled") == Some("true") {
+        debug!("{} is disabled by default", path.display());
+        return None;
+    }
+
+    Some(SearchProvider {
+        desktop_id: section.attr("DesktopId")?.to_string(),
+        bus_name: section.attr("BusName")?.to_string(),
+        object_path: section.attr("ObjectPath")?.to_string(),
+    })
+}
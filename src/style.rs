@@ -76,6 +76,85 @@ fn load_css_styles() -> String {
             font-style: italic;
             opacity: 0.8;
         }
+        
+        /* Secondary action styling */
+        .secondary-action {
+            font-weight: bold;
+        }
+        
+        /* Launch error styling */
+        .error-title {
+            font-weight: bold;
+            color: @error_color;
+        }
+        
+        .error-message {
+            font-family: monospace;
+            font-size: 0.9em;
+        }
+        
+        /* Custom command parameter styling */
+        .parameter {
+            font-weight: bold;
+        }
+        
+        /* Confirmation styling */
+        .confirm {
+            font-weight: bold;
+            color: @error_color;
+        }
+        
+        .cancel {
+            font-weight: bold;
+        }
+        
+        .pending {
+            font-style: italic;
+        }
+        
+        /* Inline command output styling */
+        .output-line {
+            font-family: monospace;
+        }
+
+        /* Preview pane */
+        .preview-pane {
+            margin: 0 12px 12px 0;
+        }
+
+        .preview {
+            padding: 4px 8px;
+        }
+
+        .preview-title {
+            font-weight: bold;
+            font-size: 1.1em;
+            margin-bottom: 4px;
+        }
+
+        .preview-key {
+            font-size: 0.9em;
+            opacity: 0.7;
+            margin-top: 6px;
+        }
+
+        .preview-text {
+            font-family: monospace;
+            font-size: 0.9em;
+            margin-top: 6px;
+        }
+
+        .preview-image {
+            margin-top: 6px;
+        }
+
+        /* Script provider, plugin, search provider and runner rows */
+        .script-item,
+        .plugin-item,
+        .provider-result,
+        .runner-match {
+            margin-left: 4px;
+        }
     "#
     .to_string()
 }
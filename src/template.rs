@@ -0,0 +1,313 @@
+use std::collections::BTreeSet;
+
+/// Placeholder replaced by every typed argument
+const ALL_ARGUMENTS: &str = "{args}";
+/// Placeholder replaced by the arguments after the last positional one used
+const REMAINING_ARGUMENTS: &str = "{rest}";
+
+/// Splits a command line into words the way a shell would, without expanding anything
+///
+/// Supports single quotes, double quotes and backslash escapes.
+///
+/// # Arguments
+/// * `line` - The command line to split
+///
+/// # Returns
+/// The words, or an error message if a quote isn't closed
+pub fn split(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if let Some(word) = word.take() {
+                    words.push(word);
+                }
+            }
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err(format!("Unclosed single quote in {line}")),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err(format!("Unclosed double quote in {line}")),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err(format!("Unclosed double quote in {line}")),
+                    }
+                }
+            }
+            '\\' => {
+                if let Some(c) = chars.next() {
+                    word.get_or_insert_with(String::new).push(c);
+                }
+            }
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+
+    words.extend(word);
+    Ok(words)
+}
+
+/// Quotes a word so a shell reads it back unchanged
+///
+/// # Arguments
+/// * `word` - The word to quote
+///
+/// # Returns
+/// The word itself if it only has safe characters, single quoted otherwise
+pub fn quote(word: &str) -> String {
+    let is_safe = !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=@%+,~".contains(c));
+
+    if is_safe {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', r"'\''"))
+    }
+}
+
+/// Builds an argv from a command template and the typed arguments
+///
+/// `{args}` stands for every argument, `{1}`, `{2}`… for a single one and `{rest}` for
+/// those after the last positional placeholder. A placeholder making up a whole word
+/// expands to one argv entry per argument. Without any placeholder the arguments are
+/// appended to the command.
+///
+/// # Arguments
+/// * `template` - The command words, possibly containing placeholders
+/// * `arguments` - The arguments typed after the command name
+///
+/// # Returns
+/// The argv, or an error message if the arguments can't be parsed or one is missing
+pub fn expand(template: &[String], arguments: Option<&str>) -> Result<Vec<String>, String> {
+    let arguments: Vec<String> = split(arguments.unwrap_or_default())?
+        .into_iter()
+        .map(|argument| expand_home(&argument))
+        .collect();
+
+    let positions = positional_placeholders(template);
+    let rest_start = positions.last().copied().unwrap_or(0);
+    let has_placeholders = !positions.is_empty()
+        || template
+            .iter()
+            .any(|word| word.contains(ALL_ARGUMENTS) || word.contains(REMAINING_ARGUMENTS));
+
+    let remaining = &arguments[rest_start.min(arguments.len())..];
+
+    let mut argv = Vec::new();
+    for word in template {
+        match word.as_str() {
+            ALL_ARGUMENTS => argv.extend(arguments.iter().cloned()),
+            REMAINING_ARGUMENTS => argv.extend(remaining.iter().cloned()),
+            _ => {
+                let mut word = word
+                    .replace(ALL_ARGUMENTS, &arguments.join(" "))
+                    .replace(REMAINING_ARGUMENTS, &remaining.join(" "));
+                for position in &positions {
+                    let placeholder = format!("{{{position}}}");
+                    if word.contains(&placeholder) {
+                        let argument = arguments
+                            .get(position - 1)
+                            .ok_or_else(|| format!("Missing argument {position}"))?;
+                        word = word.replace(&placeholder, argument);
+                    }
+                }
+                argv.push(word);
+            }
+        }
+    }
+
+    if !has_placeholders {
+        argv.extend(arguments);
+    }
+
+    Ok(argv)
+}
+
+/// Replaces named placeholders like `{host}` by their values
+///
+/// A placeholder making up a whole word is dropped when its value is empty.
+///
+/// # Arguments
+/// * `template` - The command words
+/// * `values` - The parameter names and their values
+///
+/// # Returns
+/// The command words with the values filled in
+pub fn substitute(template: &[String], values: &[(String, String)]) -> Vec<String> {
+    template
+        .iter()
+        .filter(|word| {
+            !values
+                .iter()
+                .any(|(name, value)| value.is_empty() && **word == format!("{{{name}}}"))
+        })
+        .map(|word| {
+            values.iter().fold(word.clone(), |word, (name, value)| {
+                word.replace(&format!("{{{name}}}"), value)
+            })
+        })
+        .collect()
+}
+
+/// Replaces named placeholders in a shell command line by their quoted values
+///
+/// # Arguments
+/// * `line` - The command line
+/// * `values` - The parameter names and their values
+///
+/// # Returns
+/// The command line with the values filled in
+pub fn substitute_line(line: &str, values: &[(String, String)]) -> String {
+    values.iter().fold(line.to_string(), |line, (name, value)| {
+        line.replace(&format!("{{{name}}}"), &quote(value))
+    })
+}
+
+fn positional_placeholders(template: &[String]) -> Vec<usize> {
+    let mut positions = BTreeSet::new();
+
+    for word in template {
+        let mut rest = word.as_str();
+        while let Some(start) = rest.find('{') {
+            rest = &rest[start + 1..];
+            if let Some(end) = rest.find('}')
+                && let Ok(position) = rest[..end].parse::<usize>()
+                && position > 0
+            {
+                positions.insert(position);
+            }
+        }
+    }
+
+    positions.into_iter().collect()
+}
+
+/// Expands a leading `~` to the home directory
+///
+/// # Arguments
+/// * `argument` - A path typed by the user
+///
+/// # Returns
+/// The path, starting with the home directory instead of `~`
+pub fn expand_home(argument: &str) -> String {
+    let rest = match argument.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => rest,
+        _ => return argument.to_string(),
+    };
+
+    match dirs::home_dir() {
+        Some(home) => format!("{}{rest}", home.display()),
+        None => argument.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    #[test]
+    fn splits_like_a_shell() {
+        assert_eq!(
+            split(r#"ssh -p 22 'my host' "a \"b\" \c" d\ e"#).unwrap(),
+            words(&["ssh", "-p", "22", "my host", r#"a "b" \c"#, "d e"])
+        );
+        assert_eq!(split("a''b \"\"").unwrap(), words(&["ab", ""]));
+        assert_eq!(split("  ").unwrap(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn refuses_unclosed_quotes() {
+        assert!(split("echo 'hello").is_err());
+        assert!(split("echo \"hello").is_err());
+        assert!(split("echo \"hello\\").is_err());
+    }
+
+    #[test]
+    fn quotes_what_a_shell_would_change() {
+        for word in ["plain", "it's", "a b", "$HOME", ""] {
+            assert_eq!(split(&quote(word)).unwrap(), words(&[word]));
+        }
+        assert_eq!(quote("a-b/c.d"), "a-b/c.d");
+    }
+
+    #[test]
+    fn appends_arguments_without_placeholders() {
+        assert_eq!(
+            expand(&words(&["ls", "-l"]), Some("/tmp 'a b'")).unwrap(),
+            words(&["ls", "-l", "/tmp", "a b"])
+        );
+        assert_eq!(expand(&words(&["ls"]), None).unwrap(), words(&["ls"]));
+    }
+
+    #[test]
+    fn expands_placeholders() {
+        let template = words(&["ssh", "-p", "{2}", "{1}", "{rest}"]);
+        assert_eq!(
+            expand(&template, Some("host 22 uptime -p")).unwrap(),
+            words(&["ssh", "-p", "22", "host", "uptime", "-p"])
+        );
+
+        let template = words(&["echo", "{args}", "[{args}]"]);
+        assert_eq!(
+            expand(&template, Some("a b")).unwrap(),
+            words(&["echo", "a", "b", "[a b]"])
+        );
+    }
+
+    #[test]
+    fn reports_missing_arguments() {
+        let template = words(&["ssh", "{1}", "{2}"]);
+        assert_eq!(
+            expand(&template, Some("host")),
+            Err("Missing argument 2".to_string())
+        );
+    }
+
+    #[test]
+    fn expands_home_only_at_the_start() {
+        let home = dirs::home_dir().unwrap().display().to_string();
+        assert_eq!(expand_home("~/notes"), format!("{home}/notes"));
+        assert_eq!(expand_home("~"), home);
+        assert_eq!(expand_home("~user/notes"), "~user/notes");
+        assert_eq!(expand_home("a/~"), "a/~");
+    }
+
+    #[test]
+    fn substitutes_named_values() {
+        let values = vec![
+            ("host".to_string(), "my host".to_string()),
+            ("port".to_string(), String::new()),
+        ];
+        assert_eq!(
+            substitute(&words(&["ssh", "{port}", "{host}", "-o{port}"]), &values),
+            words(&["ssh", "my host", "-o"])
+        );
+        assert_eq!(substitute_line("ssh {host}", &values), "ssh 'my host'");
+    }
+}